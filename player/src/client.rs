@@ -3,7 +3,10 @@ use futures::prelude::*;
 use log::*;
 use std::convert::AsRef;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use structopt::StructOpt;
 use tokio::net::TcpStream;
@@ -11,6 +14,10 @@ use tokio_serde::formats::Json;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use zerosumrs::ai::Ai;
 use zerosumrs::default_heuristic::DefaultHeuristic;
+use zerosumrs::game::{Game, State as GameState};
+use zerosumrs::limits::Limits;
+use zerosumrs::record::{self, GameRecord};
+use zerosumrs::tablut::Tablut;
 
 use crate::common::{Action, State, Turn};
 use crate::player::Player;
@@ -60,6 +67,22 @@ pub struct PlayerComm {
 
     /// Which Ai to use
     player: Option<Player<DefaultHeuristic>>,
+
+    /// Save the game record to this file when the game ends
+    #[structopt(long, parse(from_os_str))]
+    record: Option<PathBuf>,
+
+    /// Stop searching after this depth, even with time left
+    #[structopt(long)]
+    depth: Option<u32>,
+
+    /// Stop searching after this many positions, even with time left
+    #[structopt(long)]
+    nodes: Option<u64>,
+
+    /// Seed the random engines with this, to replay a game
+    #[structopt(long)]
+    seed: Option<u64>,
 }
 
 impl PlayerComm {
@@ -85,21 +108,96 @@ impl PlayerComm {
         let mut num_turns = 0;
         let mut player = self.player.unwrap_or_default();
         println!("Using player {}", player.as_ref());
+        // score and depth of the last report, recorded with our move
+        let last_info = Arc::new(Mutex::new(None));
+        let observed = last_info.clone();
+        player.set_observer(Box::new(move |info| {
+            info!("{}", info);
+            *observed.lock().unwrap() = Some((info.score, info.depth));
+        }));
+        // what the search said about the move we sent, until the server echoes it
+        let mut sent_info: Option<(Option<i64>, u32)> = None;
+        let mut game_record = GameRecord::<Tablut>::new();
+        let (name_tag, engine_tag) = match self.role {
+            Role::White => (record::WHITE, record::WHITE_ENGINE),
+            Role::Black => (record::BLACK, record::BLACK_ENGINE),
+        };
+        game_record.set_tag(name_tag, &self.player_name);
+        game_record.set_tag(engine_tag, player.as_ref());
+        game_record.set_tag(record::TIME_LIMIT, self.timeout.as_millis());
+        if let Some(seed) = self.seed {
+            player.set_seed(seed);
+            game_record.set_tag(record::SEED, seed);
+        }
         let outcome = loop {
-            let new_state: State = comm_stream.next().await.expect("empty stream")?;
+            let new_state: State = if turn && num_turns > 0 {
+                // the opponent is thinking, so can we
+                let stop = Arc::new(AtomicBool::new(false));
+                let pondering = {
+                    let stop = stop.clone();
+                    let mut player = player;
+                    tokio::task::spawn_blocking(move || {
+                        player.ponder(stop);
+                        player
+                    })
+                };
+                let new_state = comm_stream.next().await;
+                stop.store(true, Ordering::Relaxed);
+                player = pondering.await?;
+                new_state.expect("empty stream")?
+            } else {
+                comm_stream.next().await.expect("empty stream")?
+            };
             if !matches!(new_state.turn, Turn::WHITE | Turn::BLACK) {
+                // the move that ended the game, only needed for the record
+                if let Ok(mov) = crate::util::mov_from_state(player.get_game().clone(), &new_state)
+                {
+                    let info = sent_info.take();
+                    game_record.push_with_info(mov, info.and_then(|i| i.0), info.map(|i| i.1));
+                }
                 break new_state.turn;
             }
 
             if num_turns > 0 {
-                let mov = crate::util::mov_from_state(*player.get_game(), &new_state)?;
+                let mov = crate::util::mov_from_state(player.get_game().clone(), &new_state)?;
                 player.mov(&mov);
+                let info = sent_info.take();
+                game_record.push_with_info(mov, info.and_then(|i| i.0), info.map(|i| i.1));
             }
 
             if turn {
                 println!("Calculating next move...");
                 player.print2game();
-                let mov = player.get_mov(self.timeout);
+                let limits = Limits {
+                    time: Some(self.timeout),
+                    depth: self.depth,
+                    nodes: self.nodes,
+                };
+                // the engine keeps to its limits, the timer is only a backstop
+                let stop = Arc::new(AtomicBool::new(false));
+                player.set_stop(stop.clone());
+                // pondering reports too, only the search for the move counts
+                last_info.lock().unwrap().take();
+                let mut thinking = tokio::task::spawn_blocking(move || {
+                    let mov = player.think(limits);
+                    (player, mov)
+                });
+                let (thought, mut mov) = tokio::select! {
+                    res = &mut thinking => res?,
+                    _ = tokio::time::sleep(self.timeout) => {
+                        warn!("engine ran out of time, stopping it");
+                        stop.store(true, Ordering::Relaxed);
+                        thinking.await?
+                    }
+                };
+                player = thought;
+                sent_info = last_info.lock().unwrap().take();
+                if let Err(e) = player.get_game().check_mov(&mov) {
+                    // the server would end the game, any legal move is better
+                    error!("engine chose illegal move {}: {}", mov, e);
+                    mov = player.get_game().get_moves()[0];
+                    sent_info = None;
+                }
                 comm_stream
                     .send(Action::from_move(mov, player.turn()))
                     .await?;
@@ -112,6 +210,14 @@ impl PlayerComm {
         };
 
         println!("game ended in {} turns", num_turns);
+        game_record.set_result(match outcome {
+            Turn::WHITEWIN => GameState::Win,
+            Turn::BLACKWIN => GameState::Lose,
+            _ => GameState::Draw,
+        });
+        if let Some(path) = &self.record {
+            std::fs::write(path, game_record.to_string())?;
+        }
         match (outcome, self.role) {
             (Turn::WHITEWIN, Role::White) | (Turn::BLACKWIN, Role::Black) => {
                 println!("clic click click submit world champion")
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use strum_macros::EnumString;
 use zerosumrs::game::{Game, State as GameState};
-use zerosumrs::tablut::{Tablut, Tile};
+use zerosumrs::tablut::{square_name, Move, Tablut, Tile};
 
 #[derive(Serialize, Debug)]
 pub struct Action {
@@ -12,9 +12,13 @@ pub struct Action {
 }
 
 impl Action {
-    pub fn from_move(mov: (u8, u8), turn: bool) -> Self {
-        let from = format!("{}{}", (mov.0 % 11 + 97 - 1) as char, mov.0 / 11);
-        let to = format!("{}{}", (mov.1 % 11 + 97 - 1) as char, mov.1 / 11);
+    pub fn from_move(mov: Move, turn: bool) -> Self {
+        // a side without moves has lost, it sends "none" like Move's Display
+        let (from, to) = if mov.is_none() {
+            (mov.to_string(), mov.to_string())
+        } else {
+            (square_name(mov.from), square_name(mov.to))
+        };
         let _turn = if turn { Turn::WHITE } else { Turn::BLACK };
         Action { from, to, _turn }
     }
@@ -16,6 +16,8 @@ pub enum Error {
     StateDeserialize(String),
     #[error("Could not find a valid move for received state {0:?}")]
     InvalidNextState(Box<common::State>),
+    #[error("Engine thread failed")]
+    Engine(#[from] tokio::task::JoinError),
 }
 
 #[tokio::main]
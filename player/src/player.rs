@@ -1,10 +1,14 @@
 use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use strum_macros::AsRefStr;
 use thiserror::Error;
 use zerosumrs::ai::Ai;
 use zerosumrs::default_heuristic::DefaultHeuristic;
 use zerosumrs::game::{Game, State};
 use zerosumrs::heuristic::Heuristic;
+use zerosumrs::info::Observer;
+use zerosumrs::limits::Limits;
 use zerosumrs::tablut::Tablut;
 use zerosumrs::*;
 
@@ -14,7 +18,13 @@ pub enum Player<H: Heuristic<Tablut>> {
     MinimaxFinal(minimax_final::MinimaxFinal<Tablut, H>),
     MinimaxKiller(minimax_killer::MinimaxKiller<Tablut, H>),
     MinimaxKillerB(minimax_killer_b::MinimaxKillerB<Tablut, H>),
+    LazySmp(lazy_smp::LazySmp<Tablut, H>),
     Mcts(monte_carlo_tree_search::MonteCarloTreeSearch<Tablut>),
+    // playouts of 20 plies, greedy on the heuristic 90% of the time
+    GuidedMcts(
+        monte_carlo_tree_search::MonteCarloTreeSearch<Tablut, rollout::HeuristicRollout<H, 10, 20>>,
+    ),
+    ParallelMcts(parallel_mcts::ParallelMcts<Tablut>),
 }
 
 impl Ai<Tablut> for Player<DefaultHeuristic> {
@@ -28,7 +38,10 @@ impl Ai<Tablut> for Player<DefaultHeuristic> {
             Self::MinimaxKiller(player) => player.state(),
             Self::MinimaxKillerB(player) => player.state(),
             Self::MinimaxFinal(player) => player.state(),
+            Self::LazySmp(player) => player.state(),
             Self::Mcts(player) => player.state(),
+            Self::GuidedMcts(player) => player.state(),
+            Self::ParallelMcts(player) => player.state(),
         }
     }
 
@@ -38,7 +51,10 @@ impl Ai<Tablut> for Player<DefaultHeuristic> {
             Self::MinimaxKiller(player) => player.print2game(),
             Self::MinimaxKillerB(player) => player.print2game(),
             Self::MinimaxFinal(player) => player.print2game(),
+            Self::LazySmp(player) => player.print2game(),
             Self::Mcts(player) => player.print2game(),
+            Self::GuidedMcts(player) => player.print2game(),
+            Self::ParallelMcts(player) => player.print2game(),
         }
     }
 
@@ -48,17 +64,23 @@ impl Ai<Tablut> for Player<DefaultHeuristic> {
             Self::MinimaxKiller(player) => player.turn(),
             Self::MinimaxKillerB(player) => player.turn(),
             Self::MinimaxFinal(player) => player.turn(),
+            Self::LazySmp(player) => player.turn(),
             Self::Mcts(player) => player.turn(),
+            Self::GuidedMcts(player) => player.turn(),
+            Self::ParallelMcts(player) => player.turn(),
         }
     }
 
-    fn get_mov(&mut self, tl: std::time::Duration) -> <Tablut as Game>::M {
+    fn think(&mut self, limits: Limits) -> <Tablut as Game>::M {
         match self {
-            Self::MinimaxSimple(ref mut player) => player.get_mov(tl),
-            Self::MinimaxKiller(ref mut player) => player.get_mov(tl),
-            Self::MinimaxKillerB(ref mut player) => player.get_mov(tl),
-            Self::MinimaxFinal(ref mut player) => player.get_mov(tl),
-            Self::Mcts(ref mut player) => player.get_mov(tl),
+            Self::MinimaxSimple(ref mut player) => player.think(limits),
+            Self::MinimaxKiller(ref mut player) => player.think(limits),
+            Self::MinimaxKillerB(ref mut player) => player.think(limits),
+            Self::MinimaxFinal(ref mut player) => player.think(limits),
+            Self::LazySmp(ref mut player) => player.think(limits),
+            Self::Mcts(ref mut player) => player.think(limits),
+            Self::GuidedMcts(ref mut player) => player.think(limits),
+            Self::ParallelMcts(ref mut player) => player.think(limits),
         }
     }
 
@@ -68,7 +90,10 @@ impl Ai<Tablut> for Player<DefaultHeuristic> {
             Self::MinimaxKiller(ref mut player) => player.mov(m),
             Self::MinimaxKillerB(ref mut player) => player.mov(m),
             Self::MinimaxFinal(ref mut player) => player.mov(m),
+            Self::LazySmp(ref mut player) => player.mov(m),
             Self::Mcts(ref mut player) => player.mov(m),
+            Self::GuidedMcts(ref mut player) => player.mov(m),
+            Self::ParallelMcts(ref mut player) => player.mov(m),
         }
     }
 
@@ -78,7 +103,62 @@ impl Ai<Tablut> for Player<DefaultHeuristic> {
             Self::MinimaxKiller(player) => player.get_game(),
             Self::MinimaxKillerB(player) => player.get_game(),
             Self::MinimaxFinal(player) => player.get_game(),
+            Self::LazySmp(player) => player.get_game(),
             Self::Mcts(player) => player.get_game(),
+            Self::GuidedMcts(player) => player.get_game(),
+            Self::ParallelMcts(player) => player.get_game(),
+        }
+    }
+
+    fn ponder(&mut self, stop: Arc<AtomicBool>) {
+        match self {
+            Self::MinimaxSimple(ref mut player) => player.ponder(stop),
+            Self::MinimaxKiller(ref mut player) => player.ponder(stop),
+            Self::MinimaxKillerB(ref mut player) => player.ponder(stop),
+            Self::MinimaxFinal(ref mut player) => player.ponder(stop),
+            Self::LazySmp(ref mut player) => player.ponder(stop),
+            Self::Mcts(ref mut player) => player.ponder(stop),
+            Self::GuidedMcts(ref mut player) => player.ponder(stop),
+            Self::ParallelMcts(ref mut player) => player.ponder(stop),
+        }
+    }
+
+    fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+        match self {
+            Self::MinimaxSimple(ref mut player) => player.set_stop(stop),
+            Self::MinimaxKiller(ref mut player) => player.set_stop(stop),
+            Self::MinimaxKillerB(ref mut player) => player.set_stop(stop),
+            Self::MinimaxFinal(ref mut player) => player.set_stop(stop),
+            Self::LazySmp(ref mut player) => player.set_stop(stop),
+            Self::Mcts(ref mut player) => player.set_stop(stop),
+            Self::GuidedMcts(ref mut player) => player.set_stop(stop),
+            Self::ParallelMcts(ref mut player) => player.set_stop(stop),
+        }
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        match self {
+            Self::MinimaxSimple(ref mut player) => player.set_seed(seed),
+            Self::MinimaxKiller(ref mut player) => player.set_seed(seed),
+            Self::MinimaxKillerB(ref mut player) => player.set_seed(seed),
+            Self::MinimaxFinal(ref mut player) => player.set_seed(seed),
+            Self::LazySmp(ref mut player) => player.set_seed(seed),
+            Self::Mcts(ref mut player) => player.set_seed(seed),
+            Self::GuidedMcts(ref mut player) => player.set_seed(seed),
+            Self::ParallelMcts(ref mut player) => player.set_seed(seed),
+        }
+    }
+
+    fn set_observer(&mut self, observer: Observer<<Tablut as Game>::M>) {
+        match self {
+            Self::MinimaxSimple(ref mut player) => player.set_observer(observer),
+            Self::MinimaxKiller(ref mut player) => player.set_observer(observer),
+            Self::MinimaxKillerB(ref mut player) => player.set_observer(observer),
+            Self::MinimaxFinal(ref mut player) => player.set_observer(observer),
+            Self::LazySmp(ref mut player) => player.set_observer(observer),
+            Self::Mcts(ref mut player) => player.set_observer(observer),
+            Self::GuidedMcts(ref mut player) => player.set_observer(observer),
+            Self::ParallelMcts(ref mut player) => player.set_observer(observer),
         }
     }
 }
@@ -104,6 +184,27 @@ impl FromStr for Player<DefaultHeuristic> {
         if s.contains("final") {
             return Ok(Player::MinimaxFinal(minimax_final::MinimaxFinal::new(true)));
         }
+        if s.contains("smp") {
+            // smp4 for 4 threads, one per core without a number
+            let threads = s.trim_start_matches(|c: char| !c.is_ascii_digit());
+            return Ok(Player::LazySmp(match threads.parse() {
+                Ok(n) => lazy_smp::LazySmp::with_threads(true, n),
+                Err(_) => lazy_smp::LazySmp::new(true),
+            }));
+        }
+        if s.contains("pmcts") {
+            // pmcts4 for 4 threads, one per core without a number
+            let threads = s.trim_start_matches(|c: char| !c.is_ascii_digit());
+            return Ok(Player::ParallelMcts(match threads.parse() {
+                Ok(n) => parallel_mcts::ParallelMcts::with_threads(true, n),
+                Err(_) => parallel_mcts::ParallelMcts::new(true),
+            }));
+        }
+        if s.contains("gmcts") {
+            return Ok(Player::GuidedMcts(
+                monte_carlo_tree_search::MonteCarloTreeSearch::new(true),
+            ));
+        }
         if s.contains("mcts") {
             return Ok(Player::Mcts(
                 monte_carlo_tree_search::MonteCarloTreeSearch::new(true),
@@ -1,11 +1,31 @@
 use crate::game::*;
+use crate::info::Observer;
+use crate::limits::Limits;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 pub trait Ai<G: Game> {
 	fn new(t: bool) -> Self;
 	fn state(&self) -> State;
 	fn print2game(&self);
 	fn turn(&self) -> bool;
-	fn get_mov(&mut self, tl: std::time::Duration) -> G::M;
+	// best move found within tl
+	fn get_mov(&mut self, tl: std::time::Duration) -> G::M {
+		self.think(Limits::time(tl))
+	}
+	// best move found before any of the limits is hit
+	fn think(&mut self, limits: Limits) -> G::M;
 	fn mov(&mut self, m: &G::M);
 	fn get_game(&self) -> &G;
+	// think on the opponent's time until stop is set, keeping whatever helps
+	// the next get_mov. engines that can't ponder return straight away
+	fn ponder(&mut self, _stop: Arc<AtomicBool>) {}
+	// once stop is set the search returns soon with the best move found so
+	// far. the flag stays set, clear it or set a new one for the next search
+	fn set_stop(&mut self, stop: Arc<AtomicBool>);
+	// engines that play at random draw from seed instead of entropy, so
+	// their games can be replayed. the others ignore it
+	fn set_seed(&mut self, _seed: u64) {}
+	// receives the progress of every search, nothing is printed without one
+	fn set_observer(&mut self, observer: Observer<G::M>);
 }
@@ -1,6 +1,8 @@
+use rustc_hash::FxHasher;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::hash::Hash;
+use std::hash::Hasher;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum State {
@@ -13,13 +15,99 @@ pub trait Game: Clone + Debug + Display {
 	type M: Copy + Eq + Debug + Default;
 	type S: Hash + Copy + Eq + Debug;
 	type R: Copy + Debug + Default;
+	// why a move can't be played
+	type E: Debug + Display;
 	fn new(t: bool) -> Self;
 	fn turn(&self) -> bool;
-	fn get_moves(&self) -> Vec<Self::M>;
-	fn get_moves_sorted(&self) -> Vec<Self::M>;
+	// clear moves and fill it with the legal moves, so searchers can reuse the buffer
+	fn fill_moves(&self, moves: &mut Vec<Self::M>);
+	// same moves, most promising first
+	fn fill_moves_sorted(&self, moves: &mut Vec<Self::M>);
+	// moves worth searching past the horizon, like captures, none by default
+	fn fill_forcing_moves(&self, moves: &mut Vec<Self::M>) {
+		moves.clear();
+	}
+	// moves go between SQUARES squares, so tables can be indexed by from and to,
+	// 0 when moves can't be indexed like that
+	const SQUARES: usize = 0;
+	fn squares(_m: &Self::M) -> (usize, usize) {
+		(0, 0)
+	}
+	// inverse of squares, the default move if there is no such move
+	fn from_squares(_from: usize, _to: usize) -> Self::M {
+		Self::M::default()
+	}
+	// whether m is one of the forcing moves, the search doesn't reduce those
+	fn is_forcing(&self, _m: &Self::M) -> bool {
+		false
+	}
+	// the side to move has to answer a threat, like a check, so it can't
+	// just stand on the current position
+	fn is_threatened(&self) -> bool {
+		false
+	}
+	fn get_moves(&self) -> Vec<Self::M> {
+		let mut ans = Vec::new();
+		self.fill_moves(&mut ans);
+		ans
+	}
+	fn get_moves_sorted(&self) -> Vec<Self::M> {
+		let mut ans = Vec::new();
+		self.fill_moves_sorted(&mut ans);
+		ans
+	}
 	fn get_static_state(&self) -> Self::S;
+	// 64 bit key of the static state, games should override this with something cheaper
+	fn get_hash(&self) -> u64 {
+		let mut h = FxHasher::default();
+		self.get_static_state().hash(&mut h);
+		h.finish()
+	}
+	// same key for positions equivalent under the symmetries of the game
+	fn get_canonical_hash(&self) -> u64 {
+		self.get_hash()
+	}
 	fn state(&self) -> State;
+	// Ok if m is legal in the current position
+	fn check_mov(&self, m: &Self::M) -> Result<(), Self::E>;
+	fn is_legal(&self, m: &Self::M) -> bool {
+		self.check_mov(m).is_ok()
+	}
+	// mov that leaves the game untouched if the move is illegal
+	fn try_mov(&mut self, m: &Self::M) -> Result<(), Self::E> {
+		self.check_mov(m)?;
+		self.mov(m);
+		Ok(())
+	}
 	fn mov(&mut self, m: &Self::M);
 	fn mov_with_rollback(&mut self, m: &Self::M) -> Self::R;
 	fn rollback(&mut self, rb: Self::R);
+	// pass the turn without moving, None if the game doesn't allow it
+	fn null_mov(&mut self) -> Option<Self::R> {
+		None
+	}
+}
+
+// one move list per remaining depth, reused by every node searched at that depth
+#[derive(Debug, Clone)]
+pub struct MoveBuffers<M>(Vec<Vec<M>>);
+
+impl<M> Default for MoveBuffers<M> {
+	fn default() -> Self {
+		MoveBuffers(Vec::new())
+	}
+}
+
+impl<M> MoveBuffers<M> {
+	// the buffer goes back with put once the node is done with it
+	pub fn take(&mut self, depth: u32) -> Vec<M> {
+		let d = depth as usize;
+		if self.0.len() <= d {
+			self.0.resize_with(d + 1, Vec::new);
+		}
+		std::mem::take(&mut self.0[d])
+	}
+	pub fn put(&mut self, depth: u32, moves: Vec<M>) {
+		self.0[depth as usize] = moves;
+	}
 }
@@ -2,4 +2,13 @@ use crate::game::Game;
 
 pub trait Heuristic<G: Game> {
 	fn eval(g: &G) -> i64;
+	// chance that white wins, a logistic curve over eval that is even at 0.
+	// heuristics centered elsewhere or on another scale override it
+	fn win_probability(g: &G) -> f64 {
+		logistic(Self::eval(g) as f64 / 100.0)
+	}
+}
+
+pub fn logistic(x: f64) -> f64 {
+	1.0 / (1.0 + (-x).exp())
 }
@@ -0,0 +1,80 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+// progress of a search, engines send one after each completed iteration, the
+// last one is about the move they choose
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SearchInfo<M> {
+	pub depth: u32,
+	// deepest ply reached, quiescence search included
+	pub seldepth: u32,
+	// from white's point of view, None for engines that don't evaluate
+	pub score: Option<i64>,
+	// positions searched, or playouts for the monte carlo engines
+	pub nodes: u64,
+	pub nps: u64,
+	pub elapsed: Duration,
+	// expected line, starting with the best move
+	pub pv: Vec<M>,
+}
+
+impl<M> SearchInfo<M> {
+	pub fn new(depth: u32, score: Option<i64>, nodes: u64, elapsed: Duration, pv: Vec<M>) -> Self {
+		let micros = elapsed.as_micros().max(1);
+		Self {
+			depth,
+			seldepth: depth,
+			score,
+			nodes,
+			nps: (nodes as u128 * 1_000_000 / micros) as u64,
+			elapsed,
+			pv,
+		}
+	}
+}
+
+// depth 5 seldepth 8 score 12 nodes 3000 nps 60000 time 50 pv e4-e7 a4-b4
+impl<M: Display> Display for SearchInfo<M> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "depth {} seldepth {}", self.depth, self.seldepth)?;
+		if let Some(s) = self.score {
+			write!(f, " score {}", s)?;
+		}
+		write!(
+			f,
+			" nodes {} nps {} time {}",
+			self.nodes,
+			self.nps,
+			self.elapsed.as_millis()
+		)?;
+		if !self.pv.is_empty() {
+			write!(f, " pv")?;
+			for m in self.pv.iter() {
+				write!(f, " {}", m)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+pub type Observer<M> = Box<dyn FnMut(&SearchInfo<M>) + Send>;
+
+// the observer of an engine, reports go nowhere until one is set
+pub struct Reporter<M>(Option<Observer<M>>);
+
+impl<M> Default for Reporter<M> {
+	fn default() -> Self {
+		Reporter(None)
+	}
+}
+
+impl<M> Reporter<M> {
+	pub fn set(&mut self, observer: Observer<M>) {
+		self.0 = Some(observer);
+	}
+	pub fn report(&mut self, info: &SearchInfo<M>) {
+		if let Some(o) = self.0.as_mut() {
+			o(info);
+		}
+	}
+}
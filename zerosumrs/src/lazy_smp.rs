@@ -0,0 +1,337 @@
+use crate::ai::Ai;
+use crate::game::*;
+use crate::heuristic::Heuristic;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::ordering::MoveOrdering;
+use crate::quiescence::Quiescence;
+use crate::tt::*;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+// first aspiration window around the previous score, widened on each fail
+const WINDOW: i64 = 16;
+// iterative deepening gives up here, only reached in decided positions
+const MAX_DEPTH: u32 = 64;
+
+// one search thread, a pvs of its own that only shares the table and the
+// finished flag with the others
+struct Worker<G: Game, H: Heuristic<G>, const Q: u32> {
+	g: G,
+	id: usize,
+	nnw: u8,
+	limits: Limits,
+	st: Instant,
+	ended_early: bool,
+	root_depth: u32,
+	root_best: G::M,
+	table: Arc<SharedTable<G>>,
+	// set by the first thread to run out of limits, ends the others
+	finished: Arc<AtomicBool>,
+	// set from outside
+	stop: Arc<AtomicBool>,
+	nodes: u64,
+	moves: MoveBuffers<G::M>,
+	ordering: MoveOrdering<G>,
+	q: Quiescence<G>,
+	_ph: PhantomData<H>,
+}
+
+impl<G: Game, H: Heuristic<G>, const Q: u32> Worker<G, H, Q> {
+	fn new(g: G, id: usize, table: Arc<SharedTable<G>>, finished: Arc<AtomicBool>) -> Self {
+		Self {
+			g,
+			id,
+			nnw: 0,
+			limits: Limits::default(),
+			st: Instant::now(),
+			ended_early: false,
+			root_depth: 0,
+			root_best: G::M::default(),
+			table,
+			finished,
+			stop: Arc::default(),
+			nodes: 0,
+			moves: MoveBuffers::default(),
+			ordering: MoveOrdering::default(),
+			q: Quiescence::default(),
+			_ph: PhantomData,
+		}
+	}
+	// prev is the move that led here
+	fn pvs(&mut self, mut a: i64, mut b: i64, depth: u32, prev: G::M) -> i64 {
+		if self.g.state() != State::Going || depth == 0 {
+			return self.q.eval::<H>(&mut self.g, a, b, Q);
+		}
+		self.nnw = self.nnw.wrapping_add(1);
+		self.nodes += 1;
+		if !self.ended_early && self.nnw == 0 {
+			self.ended_early = self.finished.load(Ordering::Relaxed)
+				|| self.stop.load(Ordering::Relaxed)
+				|| self.limits.expired(self.st, self.nodes + self.q.visited);
+		}
+		if self.ended_early {
+			self.finished.store(true, Ordering::Relaxed);
+			return if self.g.turn() { a } else { b };
+		}
+		let (a0, b0) = (a, b);
+		let key = self.g.get_hash();
+		let root = depth == self.root_depth;
+		let mut hash_mov = if root { Some(self.root_best) } else { None };
+		if let Some(e) = self.table.probe(key) {
+			if let Some(v) = e.cutoff(depth, a, b).filter(|_| !root) {
+				return v;
+			}
+			hash_mov = hash_mov.or(Some(e.mov));
+		}
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves_sorted(&mut moves);
+		let white = self.g.turn();
+		let ply = (self.root_depth - depth) as usize;
+		self.ordering.order(&mut moves, hash_mov, ply, white, &prev);
+		// helpers look at the root moves in another order, so the threads
+		// spread over the tree instead of all searching the same moves
+		if root && self.id > 0 && moves.len() > 1 {
+			let n = self.id % (moves.len() - 1);
+			moves[1..].rotate_left(n);
+		}
+		let mut res = if white { i64::MIN } else { i64::MAX };
+		let mut best = moves[0];
+		for (i, m) in moves.iter().enumerate() {
+			let rb = self.g.mov_with_rollback(m);
+			let h = if i == 0 {
+				self.pvs(a, b, depth - 1, *m)
+			} else {
+				let (na, nb) = if white {
+					(a, a.saturating_add(1))
+				} else {
+					(b.saturating_sub(1), b)
+				};
+				let h = self.pvs(na, nb, depth - 1, *m);
+				if h > a && h < b {
+					if white {
+						self.pvs(h, b, depth - 1, *m)
+					} else {
+						self.pvs(a, h, depth - 1, *m)
+					}
+				} else {
+					h
+				}
+			};
+			self.g.rollback(rb);
+			if self.ended_early {
+				break;
+			}
+			if white {
+				if h > res {
+					res = h;
+					best = *m;
+				}
+				a = a.max(h);
+			} else {
+				if h < res {
+					res = h;
+					best = *m;
+				}
+				b = b.min(h);
+			}
+			if a >= b {
+				self.ordering.cutoff(m, ply, white, &prev, depth);
+				break;
+			}
+		}
+		self.moves.put(depth, moves);
+		if !self.ended_early {
+			self.table
+				.store(key, res, depth, Bound::of(res, a0, b0), best);
+			if root {
+				self.root_best = best;
+			}
+		}
+		res
+	}
+	fn aspiration(&mut self, last: i64, depth: u32) -> i64 {
+		let mut delta = WINDOW;
+		let mut a = last.saturating_sub(delta);
+		let mut b = last.saturating_add(delta);
+		loop {
+			let h = self.pvs(a, b, depth, G::M::default());
+			if self.ended_early || (h > a && h < b) {
+				return h;
+			}
+			delta = delta.saturating_mul(4);
+			if h <= a {
+				a = h.saturating_sub(delta);
+			} else {
+				b = h.saturating_add(delta);
+			}
+		}
+	}
+	// iterative deepening until a limit is hit or another thread stops,
+	// returns the last completed depth with its score and move
+	fn search(&mut self, limits: Limits) -> (u32, i64, G::M) {
+		self.limits = limits;
+		self.st = Instant::now();
+		self.ended_early = false;
+		self.ordering.new_search();
+		self.nodes = 0;
+		self.q.reset_stats();
+		self.root_best = self.g.get_moves_sorted()[0];
+		let mut ans = (0, 0, self.root_best);
+		// odd helpers start one ply deeper so the threads are rarely in sync
+		let mut depth = (self.id & 1) as u32;
+		while !self.ended_early && depth < MAX_DEPTH && self.limits.allows_depth(depth + 1) {
+			depth += 1;
+			self.root_depth = depth;
+			let h = if ans.0 == 0 {
+				self.pvs(i64::MIN, i64::MAX, depth, G::M::default())
+			} else {
+				self.aspiration(ans.1, depth)
+			};
+			if !self.ended_early {
+				ans = (depth, h, self.root_best);
+			}
+		}
+		ans
+	}
+}
+
+// lazy smp: threads searching the same position share one transposition
+// table and mostly help each other through it. the deepest result wins.
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off
+pub struct LazySmp<G: Game, H: Heuristic<G>, const Q: u32 = 0> {
+	pub g: G,
+	pub threads: usize,
+	table: Arc<SharedTable<G>>,
+	finished: Arc<AtomicBool>,
+	stop: Arc<AtomicBool>,
+	workers: Vec<Worker<G, H, Q>>,
+	reporter: Reporter<G::M>,
+}
+
+impl<G: Game, H: Heuristic<G>, const Q: u32> LazySmp<G, H, Q> {
+	pub fn with_threads(t: bool, threads: usize) -> Self {
+		Self {
+			g: G::new(t),
+			threads: threads.max(1),
+			table: Arc::new(SharedTable::new(20)),
+			finished: Arc::default(),
+			stop: Arc::default(),
+			workers: Vec::new(),
+			reporter: Reporter::default(),
+		}
+	}
+}
+
+impl<G, H, const Q: u32> LazySmp<G, H, Q>
+where
+	G: Game + Send,
+	G::M: Send,
+	H: Heuristic<G> + Send,
+{
+	// all the threads search until a limit is hit or until stop is set,
+	// returns the deepest result. the nodes are split between the threads
+	fn run(&mut self, limits: Limits, stop: &Arc<AtomicBool>) -> (u32, i64, G::M) {
+		let limits = Limits {
+			nodes: limits.nodes.map(|n| n / self.threads as u64),
+			..limits
+		};
+		self.table.new_search();
+		self.finished.store(false, Ordering::Relaxed);
+		self.workers.truncate(self.threads);
+		while self.workers.len() < self.threads {
+			let id = self.workers.len();
+			let w = Worker::new(
+				self.g.clone(),
+				id,
+				self.table.clone(),
+				self.finished.clone(),
+			);
+			self.workers.push(w);
+		}
+		for w in self.workers.iter_mut() {
+			w.g = self.g.clone();
+			w.stop = stop.clone();
+		}
+		let results: Vec<_> = std::thread::scope(|s| {
+			let handles: Vec<_> = self
+				.workers
+				.iter_mut()
+				.map(|w| s.spawn(move || w.search(limits)))
+				.collect();
+			handles.into_iter().map(|h| h.join().unwrap()).collect()
+		});
+		// first deepest, so the main thread wins ties
+		results.iter().rev().max_by_key(|r| r.0).copied().unwrap()
+	}
+	// one report per move, nodes of all the threads together
+	fn report(&mut self, depth: u32, val: i64, elapsed: Duration) {
+		let nodes = self.workers.iter().map(|w| w.nodes + w.q.visited).sum();
+		let seldepth = self
+			.workers
+			.iter()
+			.map(|w| w.root_depth + w.q.max_ply)
+			.max()
+			.unwrap_or(depth);
+		let table = &self.table;
+		let pv = principal_variation(&self.g, depth, |k| table.probe(k).map(|e| e.mov));
+		let mut info = SearchInfo::new(depth, Some(val), nodes, elapsed, pv);
+		info.seldepth = seldepth.max(depth);
+		self.reporter.report(&info);
+	}
+}
+
+impl<G, H, const Q: u32> Ai<G> for LazySmp<G, H, Q>
+where
+	G: Game + Send,
+	G::M: Send,
+	H: Heuristic<G> + Send,
+{
+	// one thread per core
+	fn new(t: bool) -> Self {
+		let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+		Self::with_threads(t, threads)
+	}
+	fn state(&self) -> State {
+		self.g.state()
+	}
+	fn get_game(&self) -> &G {
+		&self.g
+	}
+	fn print2game(&self) {
+		eprintln!("{}", self.g)
+	}
+	fn turn(&self) -> bool {
+		self.g.turn()
+	}
+	// nothing to search once the game is over
+	fn think(&mut self, limits: Limits) -> G::M {
+		if self.g.state() != State::Going {
+			return G::M::default();
+		}
+		let st = Instant::now();
+		let stop = self.stop.clone();
+		let (depth, val, ans) = self.run(limits, &stop);
+		self.report(depth, val, st.elapsed());
+		ans
+	}
+	fn mov(&mut self, m: &G::M) {
+		self.g.mov(m);
+	}
+	// the shared table keeps what is found for the positions after the reply
+	fn ponder(&mut self, stop: Arc<AtomicBool>) {
+		if self.g.state() == State::Going {
+			self.run(Limits::infinite(), &stop);
+		}
+	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
+}
@@ -6,46 +6,65 @@ pub mod ai;
 pub mod default_heuristic;
 pub mod game;
 pub mod heuristic;
+pub mod info;
+pub mod lazy_smp;
+pub mod limits;
 pub mod minimax_final;
 pub mod minimax_fixed;
 pub mod minimax_hard;
 pub mod minimax_killer;
 pub mod minimax_killer_b;
 pub mod minimax_simple;
+pub mod monte_carlo_total;
+pub mod monte_carlo_tree_search;
 pub mod old_tablut;
+pub mod ordering;
+pub mod parallel_mcts;
+pub mod perft;
+pub mod pvs;
+pub mod quiescence;
+pub mod random_agent;
+pub mod record;
+pub mod rollout;
 
 pub mod tablut;
+pub mod tt;
 
 use crate::ai::*;
 use crate::game::*;
+use crate::record::*;
 use rand::prelude::SliceRandom;
 use rand::*;
 use rand_xoshiro::Xoroshiro128Plus;
 use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
 
-fn random_play<G: Game>() -> (State, usize) {
-	let mut rng = Xoroshiro128Plus::from_rng(rand::thread_rng()).unwrap();
+fn random_play<G: Game>(rng: &mut Xoroshiro128Plus) -> (State, usize) {
 	let mut g = G::new(true);
 	let mut nmovs = 0;
+	let mut moves = Vec::new();
 	while g.state() == State::Going {
-		let moves = g.get_moves();
-		let m = moves.choose(&mut rng).unwrap();
-		g.mov(&m);
+		g.fill_moves(&mut moves);
+		let m = moves.choose(rng).unwrap();
+		g.mov(m);
 		nmovs += 1;
 	}
 	(g.state(), nmovs)
 }
 
-pub fn print_stats<G: Game>() {
+// outcomes of random games, the same for the same seed
+pub fn print_stats<G: Game>(seed: u64) {
+	let mut rng = Xoroshiro128Plus::seed_from_u64(seed);
 	let mut nw = 0;
 	let mut nl = 0;
 	let mut nd = 0;
 	let mut ne = 0;
 	let mut al = 0;
 	for _ in 0..1024 {
-		let cacca = random_play::<G>();
+		let cacca = random_play::<G>(&mut rng);
 		al += cacca.1;
 		match cacca.0 {
 			State::Win => {
@@ -72,21 +91,45 @@ pub fn print_stats<G: Game>() {
 	);
 }
 
-pub fn compete<G: Game + Display, A: Ai<G>, B: Ai<G>>(tl: Duration) {
+// the seed goes to both engines and into the record, replaying it gives the
+// same game as long as the engines search by nodes or depth, not by time
+pub fn compete<G: Game + Display, A: Ai<G>, B: Ai<G>>(tl: Duration, seed: u64) -> GameRecord<G>
+where
+	G::M: Display,
+{
 	eprintln!(
-		"Start {} vs {} in {}",
+		"Start {} vs {} in {} seed {}",
 		std::any::type_name::<A>(),
 		std::any::type_name::<B>(),
-		std::any::type_name::<G>()
+		std::any::type_name::<G>(),
+		seed
 	);
 	let mut a = A::new(true);
 	let mut b = B::new(true);
+	a.set_seed(seed);
+	b.set_seed(seed.wrapping_add(1));
+	// score and depth of the last report, recorded with the move
+	let last = Arc::new(Mutex::new(None));
+	let (la, lb) = (last.clone(), last.clone());
+	a.set_observer(Box::new(move |info| {
+		eprintln!("white {}", info);
+		*la.lock().unwrap() = Some((info.score, info.depth));
+	}));
+	b.set_observer(Box::new(move |info| {
+		eprintln!("black {}", info);
+		*lb.lock().unwrap() = Some((info.score, info.depth));
+	}));
 	let mut tta = Duration::ZERO;
 	let mut ttb = Duration::ZERO;
 	let mut mta = Duration::ZERO;
 	let mut mtb = Duration::ZERO;
 	let mut na = 0;
 	let mut nb = 0;
+	let mut record = GameRecord::new();
+	record.set_tag(WHITE_ENGINE, std::any::type_name::<A>());
+	record.set_tag(BLACK_ENGINE, std::any::type_name::<B>());
+	record.set_tag(SEED, seed);
+	record.set_tag(TIME_LIMIT, tl.as_millis());
 	a.print2game();
 	while a.state() == State::Going {
 		let tts = Instant::now();
@@ -111,8 +154,11 @@ pub fn compete<G: Game + Display, A: Ai<G>, B: Ai<G>>(tl: Duration) {
 		}
 		a.mov(&m);
 		b.mov(&m);
+		let info = last.lock().unwrap().take();
+		record.push_with_info(m, info.and_then(|i| i.0), info.map(|i| i.1));
 		a.print2game();
 	}
+	record.set_result(a.state());
 	if b.state() != a.state() {
 		eprintln!("WTF STATES ARE DESYNCED HELP!!?");
 		eprintln!(
@@ -151,6 +197,62 @@ pub fn compete<G: Game + Display, A: Ai<G>, B: Ai<G>>(tl: Duration) {
 		if a.state() == State::Win { 1 } else { 0 },
 		if a.state() == State::Lose { 1 } else { 0 }
 	);
+	record
+}
+
+// elo difference that gives an expected score of p
+fn elo(p: f64) -> f64 {
+	-400.0 * (1.0 / p - 1.0).log10()
+}
+
+// plays games between A and B swapping colours, returns the score of A from 0
+// to 1, the Elo difference it suggests and a 95% confidence interval of it.
+// a few games say next to nothing, the interval is only useful past a hundred
+// game i is played with seed + i
+pub fn elo_match<G: Game + Display, A: Ai<G>, B: Ai<G>>(
+	games: usize,
+	tl: Duration,
+	seed: u64,
+) -> (f64, f64, (f64, f64))
+where
+	G::M: Display,
+{
+	let mut scores = Vec::with_capacity(games);
+	for i in 0..games {
+		let seed = seed.wrapping_add(i as u64);
+		let s = if i % 2 == 0 {
+			compete::<G, A, B>(tl, seed).result()
+		} else {
+			compete::<G, B, A>(tl, seed).result().map(|s| match s {
+				State::Win => State::Lose,
+				State::Lose => State::Win,
+				s => s,
+			})
+		};
+		scores.push(match s {
+			Some(State::Win) => 1.0,
+			Some(State::Draw) => 0.5,
+			_ => 0.0,
+		});
+	}
+	let n = games as f64;
+	let score: f64 = scores.iter().sum();
+	let p = score / n;
+	// normal approximation over the per game scores
+	let var = scores.iter().map(|s| (s - p) * (s - p)).sum::<f64>() / n;
+	let margin = 1.96 * (var / n).sqrt();
+	let interval = (elo((p - margin).max(0.0)), elo((p + margin).min(1.0)));
+	eprintln!(
+		"{} vs {}: score {}/{} elo {:+.0} ({:+.0} to {:+.0})",
+		std::any::type_name::<A>(),
+		std::any::type_name::<B>(),
+		score,
+		games,
+		elo(p),
+		interval.0,
+		interval.1
+	);
+	(p, elo(p), interval)
 }
 
 #[cfg(test)]
@@ -158,7 +260,10 @@ mod tests {
 	use crate::ai::*;
 	use crate::default_heuristic::*;
 	use crate::game::*;
-	use crate::mancala::*;
+	use crate::heuristic::*;
+	use crate::info::*;
+	use crate::lazy_smp::*;
+	use crate::limits::*;
 	use crate::minimax_final::*;
 	use crate::minimax_fixed::*;
 	use crate::minimax_hard::*;
@@ -167,11 +272,21 @@ mod tests {
 	use crate::minimax_simple::*;
 	use crate::monte_carlo_total::*;
 	use crate::monte_carlo_tree_search::*;
-	use crate::othello::*;
+	use crate::ordering::*;
+	use crate::parallel_mcts::*;
+	use crate::perft::*;
+	use crate::pvs::*;
+	use crate::quiescence::*;
 	use crate::random_agent::*;
+	use crate::record::*;
+	use crate::rollout::*;
 	use crate::tablut::*;
-	use crate::tictactoe::*;
+	use crate::tt::*;
 	use crate::*;
+	use std::sync::atomic::AtomicBool;
+	use std::sync::atomic::Ordering;
+	use std::sync::Arc;
+	use std::sync::Mutex;
 	use std::time::Duration;
 
 	fn test_rollback<G: Game, A: Ai<G>, B: Ai<G>>() {
@@ -190,9 +305,9 @@ mod tests {
 			b.mov(&m);
 			v.push((g.clone(), rb));
 		}
-		while !v.is_empty() {
-			let x = v.pop().unwrap();
+		while let Some(x) = v.pop() {
 			assert_eq!(x.0.get_static_state(), g.get_static_state());
+			assert_eq!(x.0.get_hash(), g.get_hash());
 			if !v.is_empty() {
 				g.rollback(x.1);
 			}
@@ -208,10 +323,826 @@ mod tests {
 	}
 	#[test]
 	fn rollbacks_test() {
-		test_rollback_game::<Mancala>();
-		test_rollback_game::<Tictactoe>();
 		test_rollback_game::<Tablut>();
-		test_rollback_game::<Othello>();
+	}
+
+	#[test]
+	fn test_repetition() {
+		let mut g = Tablut::new(true);
+		let mut old = old_tablut::Tablut::new(true);
+		let mut rb = vec![];
+		// a defender and an attacker step aside and back
+		let movs = [
+			("e3-d3", (22, 21)),
+			("a4-b4", (27, 28)),
+			("d3-e3", (21, 22)),
+			("b4-a4", (28, 27)),
+		];
+		for (m, m_old) in movs.iter() {
+			assert_eq!(g.state(), State::Going);
+			rb.push(g.mov_with_rollback(&m.parse().unwrap()));
+			old.mov(m_old);
+		}
+		assert_eq!(g.state(), State::Draw);
+		assert_eq!(old.state(), State::Draw);
+		g.rollback(rb.pop().unwrap());
+		assert_eq!(g.state(), State::Going);
+		g.mov(&"b4-c4".parse().unwrap());
+		assert_eq!(g.state(), State::Going);
+	}
+
+	#[test]
+	fn test_zobrist() {
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		for _ in 0..1000 {
+			let mut g = Tablut::new(true);
+			while g.state() == State::Going {
+				let m = *g.get_moves().choose(&mut rng).unwrap();
+				g.mov(&m);
+				assert_eq!(g.get_hash(), g.zobrist());
+			}
+		}
+	}
+
+	// perft numbers cross checked against old_tablut
+	const TABLUT_PERFT: [(&str, &[u64]); 4] = [
+		(
+			"3AAA3/4A4/4D4/A3D3A/AADDKDDAA/A3D3A/4D4/4A4/3AAA3 w 0",
+			&[56, 4408, 248456, 19159224],
+		),
+		// king next to the throne, attackers can capture it with three
+		(
+			"3AAA3/4A4/A8/A2AKA2A/AAD3DAA/A3D3A/4D4/4A4/3AAA3 b 1",
+			&[102, 3216, 319358],
+		),
+		// half empty citadels, pawns inside can move through them
+		(
+			"3A1A3/4A4/9/A3D3A/AAD5A/A2K4A/4D4/9/3AAA3 w 0",
+			&[37, 3040, 106019],
+		),
+		// king one step from two escapes
+		(
+			"2A6/1K7/9/A7A/AA5AA/A7A/6D2/2D6/3AAA3 w 0",
+			&[32, 2562, 72992],
+		),
+	];
+
+	#[test]
+	fn test_perft() {
+		for (fen, counts) in TABLUT_PERFT.iter() {
+			let mut g: Tablut = fen.parse().unwrap();
+			for (depth, count) in counts.iter().enumerate() {
+				let depth = depth as u32 + 1;
+				assert_eq!(perft(&mut g, depth), *count, "depth {}\n{}", depth, g);
+			}
+			let mut g: Tablut = fen.parse().unwrap();
+			let total: u64 = divide(&mut g, 2).iter().map(|x| x.1).sum();
+			assert_eq!(total, counts[1]);
+		}
+	}
+
+	#[test]
+	fn test_rules() {
+		let presets = [Rules::ASHTON, Rules::SEABATTLE, Rules::BRANDUBH];
+		// king sandwiched away from the throne
+		let expected = [State::Lose, State::Going, State::Lose];
+		for (rules, state) in presets.iter().zip(expected.iter()) {
+			let mut g = Tablut::from_str_with_rules("9/9/9/9/9/9/1AK6/3A5/D8 b 1", *rules).unwrap();
+			g.mov(&"d8-d7".parse().unwrap());
+			assert_eq!(g.state(), *state, "{:?}", rules);
+		}
+		// the king moves next to an attacker with a defender behind it
+		let expected = [1, 2, 1];
+		for (rules, na) in presets.iter().zip(expected.iter()) {
+			let mut g =
+				Tablut::from_str_with_rules("9/9/2K2AD2/9/9/9/9/4A4/9 w 0", *rules).unwrap();
+			g.mov(&"c3-e3".parse().unwrap());
+			assert_eq!(g.a.count_ones(), *na, "{:?}", rules);
+		}
+		// the king steps on an escape square next to the corner
+		let expected = [State::Win, State::Win, State::Going];
+		for (rules, state) in presets.iter().zip(expected.iter()) {
+			let mut g = Tablut::from_str_with_rules("9/1K7/9/9/9/9/9/4A4/D8 w 0", *rules).unwrap();
+			g.mov(&"b2-a2".parse().unwrap());
+			assert_eq!(g.state(), *state, "{:?}", rules);
+			// the same position, parsed with the king already there
+			let g = Tablut::from_str_with_rules("9/K8/9/9/9/9/9/4A4/D8 b 1", *rules).unwrap();
+			assert_eq!(g.state(), *state, "{:?}", rules);
+		}
+	}
+
+	#[test]
+	fn test_fen() {
+		let g = Tablut::new(true);
+		assert_eq!(g.fen(), TABLUT_PERFT[0].0);
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		for _ in 0..100 {
+			let mut g = Tablut::new(true);
+			while g.state() == State::Going {
+				let m = *g.get_moves().choose(&mut rng).unwrap();
+				g.mov(&m);
+				let parsed: Tablut = g.fen().parse().unwrap();
+				assert_eq!(parsed.get_static_state(), g.get_static_state());
+				assert_eq!(parsed.get_hash(), g.get_hash());
+				assert_eq!(parsed.fen(), g.fen());
+			}
+		}
+		let errors = [
+			("9/9/9/9/4K4/9/9/9/9 w", ParseTablutError::Fields(2)),
+			("9/9/9/9/4K4/9/9/9 w 0", ParseTablutError::Ranks(8)),
+			("9/9/9/9/4K5/9/9/9/9 w 0", ParseTablutError::RankLength(4)),
+			("9/9/9/9/4X4/9/9/9/9 w 0", ParseTablutError::Piece('X')),
+			(
+				"9/9/9/9/4A4/9/9/9/9 w 0",
+				ParseTablutError::Square(Tile::A, (4, 4)),
+			),
+			(
+				"3D5/9/9/9/4K4/9/9/9/9 w 0",
+				ParseTablutError::Square(Tile::D, (3, 0)),
+			),
+			("9/9/9/9/3KK4/9/9/9/9 w 0", ParseTablutError::Kings(2)),
+			(
+				"9/9/9/9/4K4/9/9/9/9 x 0",
+				ParseTablutError::Side("x".to_string()),
+			),
+			(
+				"9/9/9/9/4K4/9/9/9/9 w -1",
+				ParseTablutError::Turn("-1".to_string()),
+			),
+			("9/9/9/9/4K4/9/9/9/9 w 1", ParseTablutError::SideTurn),
+		];
+		for (fen, err) in errors.iter() {
+			assert_eq!(fen.parse::<Tablut>().unwrap_err(), *err);
+		}
+	}
+
+	#[test]
+	fn test_move_notation() {
+		let g = Tablut::new(true);
+		for m in g.get_moves() {
+			assert_eq!(m.to_string().parse::<Move>(), Ok(m));
+		}
+		let m: Move = "e3-e1".parse().unwrap();
+		assert_eq!(m.coords(), Some(((4, 2), (4, 0))));
+		assert_eq!(Move::NONE.coords(), None);
+		assert_eq!(m, Move::new(square((4, 2)), square((4, 0))));
+		assert_eq!("none".parse::<Move>(), Ok(Move::NONE));
+		assert_eq!(Move::NONE.to_string(), "none");
+		for s in ["e3", "e3-j1", "e0-e1", "E3-E1", "e3-e1-e2"].iter() {
+			assert_eq!(s.parse::<Move>(), Err(ParseMoveError(s.to_string())));
+		}
+	}
+
+	#[test]
+	fn test_record() {
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		let mut g = Tablut::new(true);
+		let mut hashes = vec![g.get_hash()];
+		let mut record = GameRecord::<Tablut>::new();
+		record.set_tag(WHITE, "us");
+		record.set_tag(BLACK, "the \"others\"");
+		while g.state() == State::Going {
+			let m = *g.get_moves().choose(&mut rng).unwrap();
+			match hashes.len() % 3 {
+				0 => record.push(m),
+				1 => record.push_with_info(m, Some(-(hashes.len() as i64)), None),
+				_ => record.push_with_info(m, Some(7), Some(hashes.len() as u32)),
+			}
+			g.mov(&m);
+			hashes.push(g.get_hash());
+		}
+		record.set_result(g.state());
+		let parsed: GameRecord<Tablut> = record.to_string().parse().unwrap();
+		assert_eq!(parsed, record);
+		assert_eq!(parsed.tag(BLACK), Some("the \"others\""));
+		let positions = parsed.replay().unwrap();
+		let replayed: Vec<_> = positions.iter().map(|p| p.get_hash()).collect();
+		assert_eq!(replayed, hashes);
+		assert_eq!(positions.last().unwrap().state(), g.state());
+
+		let text = "[Result \"*\"]\n\n1. e3-d3 {depth 2} a4-b4\n2. d3-e3 *\n";
+		let record: GameRecord<Tablut> = text.parse().unwrap();
+		assert_eq!(record.to_string(), text);
+		assert_eq!(record.moves.len(), 3);
+		assert_eq!(record.moves[0].depth, Some(2));
+		let errors = [
+			(
+				"[White us]",
+				ParseRecordError::Tag("[White us]".to_string()),
+			),
+			("1. e3-z3", ParseRecordError::Move("e3-z3".to_string())),
+			(
+				"{score 1} e3-d3",
+				ParseRecordError::Orphan("score 1".to_string()),
+			),
+			(
+				"e3-d3 {score}",
+				ParseRecordError::Comment("score".to_string()),
+			),
+			("e3-d3 {score 1", ParseRecordError::Unterminated),
+		];
+		for (text, err) in errors.iter() {
+			assert_eq!(text.parse::<GameRecord<Tablut>>().unwrap_err(), *err);
+		}
+		let mut record: GameRecord<Tablut> = "e3-d3 1-0".parse().unwrap();
+		assert_eq!(
+			record.replay().unwrap_err(),
+			ReplayError::Result(State::Going)
+		);
+		record.tags.clear();
+		record.moves.insert(0, record.moves[0]);
+		record.moves[0].mov = Move::NONE;
+		assert_eq!(record.replay().unwrap_err(), ReplayError::GameOver(1));
+		// compete records what the engines report about their moves
+		let tl = Duration::ZERO;
+		type Fixed = MinimaxFixed<Tablut, DefaultHeuristic, 1>;
+		let record = compete::<Tablut, Fixed, RandomAgent<Tablut>>(tl, 0);
+		for (i, m) in record.moves.iter().enumerate() {
+			assert_eq!(m.depth, Some((i % 2 == 0) as u32));
+			assert_eq!(m.score.is_some(), i % 2 == 0);
+		}
+	}
+
+	#[test]
+	fn test_legal() {
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		for _ in 0..20 {
+			let mut g = Tablut::new(true);
+			let mut old = old_tablut::Tablut::new(true);
+			while g.state() == State::Going {
+				let moves = g.get_moves();
+				let old_moves = old.get_moves();
+				for from in 0..81 {
+					for to in 0..81 {
+						let (f, t) = ((from % 9, from / 9), (to % 9, to / 9));
+						let m = Move::from_coords(f, t);
+						let err = g.check_mov(&m).err();
+						assert_eq!(err.is_none(), moves.contains(&m), "{} {:?}", m, err);
+						if from != to {
+							assert_eq!(old.check_mov(&(from, to)).err(), err);
+							assert_eq!(old_moves.contains(&(from, to)), err.is_none());
+						}
+					}
+				}
+				assert_eq!(g.is_legal(&Move::NONE), moves[0].is_none());
+				assert_eq!(old.is_legal(&(40, 40)), moves[0].is_none());
+				let m = *moves.choose(&mut rng).unwrap();
+				// the old tablut passes with a move from and to the throne
+				let (f, t) = m.coords().unwrap_or(((4, 4), (4, 4)));
+				g.try_mov(&m).unwrap();
+				old.try_mov(&(f.0 + f.1 * 9, t.0 + t.1 * 9)).unwrap();
+			}
+		}
+		let mut g = Tablut::new(true);
+		let errors = [
+			("e5-e6", MoveError::Blocked((4, 5))),
+			("e1-e2", MoveError::WrongSide((4, 0))),
+			("a1-a2", MoveError::Empty((0, 0))),
+			("e3-f4", MoveError::NotStraight),
+			("e3-e3", MoveError::NotStraight),
+			("e4-e2", MoveError::Blocked((4, 2))),
+			("none", MoveError::Pass),
+		];
+		for (m, err) in errors.iter() {
+			let m: Move = m.parse().unwrap();
+			assert_eq!(g.try_mov(&m), Err(*err), "{}", m);
+		}
+		assert_eq!(g.turn, 0);
+		assert_eq!(
+			g.check_mov(&Move::new(11, 23)),
+			Err(MoveError::OffBoard(11))
+		);
+		assert_eq!(
+			g.check_mov(&Move::new(23, 130)),
+			Err(MoveError::OffBoard(130))
+		);
+		let k: Tablut = "A8/9/9/9/9/9/9/2K6/9 w 0".parse().unwrap();
+		let m = "c8-g8".parse().unwrap();
+		assert_eq!(k.check_mov(&m), Err(MoveError::Citadel((4, 7))));
+		g.state = State::Win;
+		assert_eq!(
+			g.check_mov(&"e3-f3".parse().unwrap()),
+			Err(MoveError::GameOver)
+		);
+	}
+
+	#[test]
+	fn test_move_classes() {
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		let mut seen = (0, 0);
+		for rules in [Rules::ASHTON, Rules::SEABATTLE, Rules::BRANDUBH].iter() {
+			for _ in 0..10 {
+				let mut g = Tablut::with_rules(true, *rules);
+				let (mut captures, mut threats, mut quiet) = (vec![], vec![], vec![]);
+				while g.state() == State::Going {
+					g.fill_captures(&mut captures);
+					g.fill_king_threats(&mut threats);
+					g.fill_quiet(&mut quiet);
+					let mut all = [&captures[..], &threats[..], &quiet[..]].concat();
+					all.sort_unstable();
+					let mut moves = g.get_moves();
+					moves.sort_unstable();
+					assert_eq!(all, moves);
+					seen.0 += captures.len();
+					seen.1 += threats.len();
+					for m in moves.iter() {
+						let mut next = g.clone();
+						let rb = next.mov_with_rollback(m);
+						let pieces = |g: &Tablut| (g.a | g.d | g.k).count_ones();
+						assert_eq!(pieces(&next) < pieces(&g), captures.contains(m));
+						assert_eq!(rb.mov(&next), *m);
+						assert_eq!(rb.capture_set(&next), g.capture_set(m));
+						let lost = pieces(&g) - pieces(&next);
+						assert_eq!(rb.captures(&next).len() as u32, lost);
+						for (t, (x, y)) in rb.captures(&next) {
+							assert_eq!(g.get_board()[y as usize][x as usize], t);
+						}
+						assert_eq!(rb.king_captured(&next), next.k == 0);
+						let escaped = next.state() == State::Win && !m.is_none();
+						assert_eq!(rb.king_escaped(&next), escaped);
+						let white = Tablut::from_board_with_rules(&next.get_board(), 0, *rules);
+						let escapes = next.state() == State::Win
+							|| white.get_moves().iter().any(|x| {
+								(white.k >> x.from) & 1 != 0 && (rules.goal() >> x.to) & 1 != 0
+							});
+						let threat = g.turn() && (g.k >> m.from) & 1 != 0 && escapes;
+						assert_eq!(threat, g.is_king_threat(m), "{}\n{}", m, g);
+					}
+					g.mov(moves.choose(&mut rng).unwrap());
+				}
+			}
+		}
+		assert!(seen.0 > 0 && seen.1 > 0);
+	}
+
+	#[test]
+	fn test_tt() {
+		assert_eq!(Bound::of(-5, -5, 5), Bound::Upper);
+		assert_eq!(Bound::of(0, -5, 5), Bound::Exact);
+		assert_eq!(Bound::of(7, -5, 5), Bound::Lower);
+		// a single bucket, to force every replacement
+		let mut tt = TranspositionTable::<u8>::new(0);
+		tt.store(1, 10, 5, Bound::Exact, 1);
+		let e = *tt.probe(1).unwrap();
+		assert_eq!((e.val, e.depth, e.mov), (10, 5, 1));
+		assert_eq!(e.cutoff(5, 0, 20), Some(10));
+		assert_eq!(e.cutoff(6, 0, 20), None);
+		tt.store(2, 10, 3, Bound::Lower, 2);
+		let e = *tt.probe(2).unwrap();
+		assert_eq!(e.cutoff(3, 0, 10), Some(10));
+		assert_eq!(e.cutoff(3, 0, 20), None);
+		tt.store(3, -10, 2, Bound::Upper, 3);
+		let e = *tt.probe(3).unwrap();
+		assert_eq!(e.cutoff(1, -10, 0), Some(-10));
+		assert_eq!(e.cutoff(1, -20, 0), None);
+		// the shallower entry went to the always-replace slot
+		assert!(tt.probe(1).is_some() && tt.probe(2).is_none());
+		tt.store(4, 0, 6, Bound::Exact, 4);
+		assert!(tt.probe(4).is_some() && tt.probe(1).is_some() && tt.probe(3).is_none());
+		// entries from older searches give way
+		tt.new_search();
+		tt.store(5, 0, 1, Bound::Exact, 5);
+		assert!(tt.probe(5).is_some() && tt.probe(4).is_some() && tt.probe(1).is_none());
+		tt.clear();
+		assert!(tt.probe(5).is_none());
+
+		let mut a = MinimaxKiller::<Tablut, DefaultHeuristic>::new(true);
+		let mut b = MinimaxHard::<Tablut, DefaultHeuristic>::new(true);
+		let tl = Duration::from_millis(30);
+		for _ in 0..20 {
+			if a.state() != State::Going {
+				break;
+			}
+			let m = match a.turn() {
+				true => a.get_mov(tl),
+				false => b.get_mov(tl),
+			};
+			assert!(a.get_game().is_legal(&m), "{}\n{}", m, a.get_game());
+			a.mov(&m);
+			b.mov(&m);
+		}
+	}
+
+	#[test]
+	fn test_quiescence() {
+		let mut q = Quiescence::<Tablut>::default();
+		let (min, max) = (i64::MIN, i64::MAX);
+		// the king can be captured, then can escape
+		let fens = [
+			("9/9/9/9/9/9/1AK6/3A5/D8 b 1", "d8-d7", State::Lose),
+			("9/1K7/9/9/9/9/9/4A4/D8 w 0", "b2-a2", State::Win),
+		];
+		for (fen, m, state) in fens.iter() {
+			let mut g: Tablut = fen.parse().unwrap();
+			let m: Move = m.parse().unwrap();
+			let mut forcing = vec![];
+			g.fill_forcing_moves(&mut forcing);
+			assert!(forcing.contains(&m));
+			let stand = DefaultHeuristic::eval(&g);
+			assert_eq!(q.eval::<DefaultHeuristic>(&mut g, min, max, 0), stand);
+			let mut next = g.clone();
+			next.mov(&m);
+			assert_eq!(next.state(), *state);
+			let best = DefaultHeuristic::eval(&next);
+			assert_eq!(q.eval::<DefaultHeuristic>(&mut g, min, max, 100), best);
+			assert_eq!(g, fen.parse().unwrap());
+		}
+		// the king threatens two escapes, the attackers have no forcing
+		// moves but can't stand pat either
+		let mut g: Tablut = "9/1K7/9/9/9/9/9/4A4/D8 b 1".parse().unwrap();
+		assert!(g.is_threatened());
+		let mut forcing = vec![];
+		g.fill_forcing_moves(&mut forcing);
+		assert!(forcing.is_empty());
+		// whatever they play, the king is out on ply 3
+		assert_eq!(q.eval::<DefaultHeuristic>(&mut g, min, max, 1000), max - 3);
+		// random positions, forcing moves only help the side to move
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		let mut g = Tablut::new(true);
+		while g.state() == State::Going {
+			let stand = DefaultHeuristic::eval(&g);
+			let v = q.eval::<DefaultHeuristic>(&mut g, min, max, 1000);
+			if !g.is_threatened() {
+				assert!(if g.turn() { v >= stand } else { v <= stand });
+			}
+			g.mov(g.get_moves().choose(&mut rng).unwrap());
+		}
+		let mut a = MinimaxFixed::<Tablut, DefaultHeuristic, 2, 1000>::new(true);
+		let m = a.get_mov(Duration::ZERO);
+		assert!(a.get_game().is_legal(&m));
+	}
+
+	#[test]
+	fn test_ordering() {
+		let g = Tablut::new(true);
+		let mut o = MoveOrdering::<Tablut>::default();
+		let moves = g.get_moves_sorted();
+		let (prev, k0, k1, c, h) = (moves[9], moves[5], moves[6], moves[7], moves[8]);
+		o.cutoff(&k1, 1, true, &Move::NONE, 1);
+		o.cutoff(&k0, 1, true, &Move::NONE, 1);
+		o.cutoff(&c, 3, true, &prev, 1);
+		o.cutoff(&h, 5, true, &Move::NONE, 4);
+		assert_eq!(o.killers(1), [k0, k1]);
+		assert_eq!(o.counter(true, &prev), Some(c));
+		assert_eq!(o.history(true, &h), 16);
+		assert_eq!(o.history(false, &h), 0);
+		let mut sorted = moves.clone();
+		o.order(&mut sorted, Some(moves[4]), 1, true, &prev);
+		assert_eq!(sorted[..5], [moves[4], k0, k1, c, h]);
+		// the rest keep their order
+		let rest: Vec<_> = moves.iter().filter(|m| !sorted[..5].contains(m)).collect();
+		assert!(sorted[5..].iter().eq(rest));
+		// other plies only see the history
+		o.order(&mut sorted, None, 2, true, &Move::NONE);
+		assert_eq!(sorted[0], h);
+		o.new_search();
+		assert_eq!(o.killers(1), [Move::NONE; 2]);
+		assert_eq!(o.history(true, &h), 8);
+	}
+
+	#[test]
+	fn test_pvs() {
+		// a null move only passes the turn
+		let mut g = Tablut::new(true);
+		let start = g.clone();
+		let rb = g.null_mov().unwrap();
+		assert!(!g.turn() && g.get_hash() != start.get_hash());
+		assert_eq!(g.get_board(), start.get_board());
+		let rb2 = g.mov_with_rollback(&g.get_moves()[0]);
+		g.rollback(rb2);
+		g.rollback(rb);
+		assert_eq!(g.get_hash(), start.get_hash());
+		assert_eq!(g, start);
+		let mut a = Pvs::<Tablut, DefaultHeuristic>::new(true);
+		let mut b = Pvs::<Tablut, DefaultHeuristic, 1000, true, true>::new(true);
+		let tl = Duration::from_millis(30);
+		for _ in 0..20 {
+			if a.state() != State::Going {
+				break;
+			}
+			let m = match a.turn() {
+				true => a.get_mov(tl),
+				false => b.get_mov(tl),
+			};
+			assert!(a.get_game().is_legal(&m), "{}\n{}", m, a.get_game());
+			a.mov(&m);
+			b.mov(&m);
+		}
+	}
+
+	#[test]
+	fn test_lazy_smp() {
+		let t = SharedTable::<Tablut>::new(4);
+		let m: Move = "e3-a3".parse().unwrap();
+		t.store(7, i64::MIN + 3, 9, Bound::Upper, m);
+		let e = t.probe(7).unwrap();
+		assert_eq!(
+			(e.val, e.depth, e.bound, e.mov),
+			(i64::MIN + 3, 9, Bound::Upper, m)
+		);
+		assert!(t.probe(7 + 16).is_none());
+		t.clear();
+		assert!(t.probe(7).is_none());
+
+		let mut a = LazySmp::<Tablut, DefaultHeuristic>::with_threads(true, 3);
+		let tl = Duration::from_millis(30);
+		for _ in 0..10 {
+			if a.state() != State::Going {
+				break;
+			}
+			let m = a.get_mov(tl);
+			assert!(a.get_game().is_legal(&m), "{}\n{}", m, a.get_game());
+			a.mov(&m);
+		}
+	}
+
+	#[test]
+	fn test_parallel_mcts() {
+		let mut a = ParallelMcts::<Tablut>::with_threads(true, 3);
+		let tl = Duration::from_millis(30);
+		for _ in 0..10 {
+			if a.state() != State::Going {
+				break;
+			}
+			let m = a.get_mov(tl);
+			assert!(a.get_game().is_legal(&m), "{}\n{}", m, a.get_game());
+			a.mov(&m);
+		}
+	}
+
+	fn test_ponder<A: Ai<Tablut>>() {
+		let mut a = A::new(true);
+		let tl = Duration::from_millis(30);
+		for _ in 0..4 {
+			let m = a.get_mov(tl);
+			a.mov(&m);
+			if a.state() != State::Going {
+				return;
+			}
+			let stop = Arc::new(AtomicBool::new(false));
+			let s = stop.clone();
+			let t = std::thread::spawn(move || {
+				std::thread::sleep(tl);
+				s.store(true, Ordering::Relaxed);
+			});
+			a.ponder(stop);
+			t.join().unwrap();
+			let m = a.get_game().get_moves()[0];
+			a.mov(&m);
+			if a.state() != State::Going {
+				return;
+			}
+		}
+		let m = a.get_mov(tl);
+		assert!(a.get_game().is_legal(&m));
+	}
+
+	#[test]
+	fn test_pondering() {
+		test_ponder::<Pvs<Tablut, DefaultHeuristic>>();
+		test_ponder::<MinimaxFinal<Tablut, DefaultHeuristic>>();
+		test_ponder::<LazySmp<Tablut, DefaultHeuristic>>();
+		test_ponder::<MonteCarloTreeSearch<Tablut>>();
+		test_ponder::<ParallelMcts<Tablut>>();
+		// engines that can't ponder just return
+		test_ponder::<MinimaxSimple<Tablut, DefaultHeuristic>>();
+	}
+
+	// the engine reports at least once per move, and the line starts legally
+	fn test_info<A: Ai<Tablut>>(limits: Limits) -> (Move, Vec<SearchInfo<Move>>) {
+		let mut a = A::new(true);
+		let infos = Arc::new(Mutex::new(Vec::new()));
+		let i = infos.clone();
+		a.set_observer(Box::new(move |info| i.lock().unwrap().push(info.clone())));
+		let m = a.think(limits);
+		let infos = infos.lock().unwrap().clone();
+		assert!(!infos.is_empty());
+		for info in infos.iter() {
+			assert!(info.nodes > 0);
+			assert!(info.seldepth >= info.depth);
+			assert!(a.get_game().is_legal(&info.pv[0]));
+		}
+		(m, infos)
+	}
+
+	#[test]
+	fn test_search_info() {
+		let tl = Limits::time(Duration::from_millis(30));
+		let (m, infos) = test_info::<Pvs<Tablut, DefaultHeuristic, 100>>(tl);
+		let last = infos.last().unwrap();
+		assert_eq!(last.pv[0], m);
+		assert!(last.score.is_some());
+		assert!(infos.windows(2).all(|w| w[0].depth < w[1].depth));
+		test_info::<MinimaxKillerB<Tablut, DefaultHeuristic>>(tl);
+		test_info::<MinimaxFinal<Tablut, DefaultHeuristic>>(tl);
+		test_info::<LazySmp<Tablut, DefaultHeuristic>>(tl);
+		let (_, infos) = test_info::<MonteCarloTreeSearch<Tablut>>(tl);
+		assert_eq!(infos[0].score, None);
+	}
+
+	// an infinite search ends once stopped from another thread
+	fn test_stop<A: Ai<Tablut>>() {
+		let mut a = A::new(true);
+		let stop = Arc::new(AtomicBool::new(false));
+		a.set_stop(stop.clone());
+		let t = std::thread::spawn(move || {
+			std::thread::sleep(Duration::from_millis(30));
+			stop.store(true, Ordering::Relaxed);
+		});
+		let st = std::time::Instant::now();
+		let m = a.think(Limits::infinite());
+		assert!(st.elapsed() < Duration::from_secs(2));
+		assert!(a.get_game().is_legal(&m));
+		t.join().unwrap();
+		// stopped before it starts, still a move
+		let mut a = A::new(true);
+		a.set_stop(Arc::new(AtomicBool::new(true)));
+		let m = a.think(Limits::nodes(0));
+		assert!(a.get_game().is_legal(&m));
+	}
+
+	// an engine that keeps its depth between moves still has to answer a
+	// depth limit it has already searched past, after a move it didn't expect
+	fn test_carried_depth<A: Ai<Tablut>>() {
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		let mut a = A::new(true);
+		while a.state() == State::Going {
+			let expected = a.think(Limits::depth(3));
+			let moves = a.get_game().get_moves();
+			let m = *moves.iter().find(|m| **m != expected).unwrap_or(&expected);
+			a.mov(&m);
+			if a.state() != State::Going {
+				break;
+			}
+			let m = a.think(Limits::depth(1));
+			assert!(a.get_game().is_legal(&m), "{}\n{}", m, a.get_game());
+			a.mov(a.get_game().get_moves().choose(&mut rng).unwrap());
+		}
+	}
+
+	#[test]
+	fn test_carried_depths() {
+		test_carried_depth::<MinimaxKiller<Tablut, DefaultHeuristic>>();
+		test_carried_depth::<MinimaxKillerB<Tablut, DefaultHeuristic>>();
+		test_carried_depth::<MinimaxFinal<Tablut, DefaultHeuristic>>();
+	}
+
+	#[test]
+	fn test_stopping() {
+		test_stop::<MinimaxSimple<Tablut, DefaultHeuristic>>();
+		test_stop::<MinimaxFixed<Tablut, DefaultHeuristic, 8>>();
+		test_stop::<MinimaxHard<Tablut, DefaultHeuristic>>();
+		test_stop::<MinimaxKiller<Tablut, DefaultHeuristic>>();
+		test_stop::<MinimaxKillerB<Tablut, DefaultHeuristic>>();
+		test_stop::<MinimaxFinal<Tablut, DefaultHeuristic>>();
+		test_stop::<Pvs<Tablut, DefaultHeuristic>>();
+		test_stop::<LazySmp<Tablut, DefaultHeuristic>>();
+		test_stop::<MonteCarloTreeSearch<Tablut>>();
+		test_stop::<ParallelMcts<Tablut>>();
+		test_stop::<MonteCarloTotal<Tablut>>();
+	}
+
+	// the same seed and node limit gives the same search
+	fn test_seed<A: Ai<Tablut>>() {
+		let search = |seed| {
+			let mut a = A::new(true);
+			a.set_seed(seed);
+			let infos = Arc::new(Mutex::new(Vec::new()));
+			let i = infos.clone();
+			// everything but the timings
+			a.set_observer(Box::new(move |info| {
+				i.lock().unwrap().push((info.nodes, info.pv.clone()))
+			}));
+			let mut moves = Vec::new();
+			for _ in 0..4 {
+				let m = a.think(Limits::nodes(256));
+				a.mov(&m);
+				moves.push(m);
+			}
+			let infos = infos.lock().unwrap().clone();
+			(moves, infos)
+		};
+		assert_eq!(search(7), search(7));
+	}
+
+	#[test]
+	fn test_seeds() {
+		test_seed::<MonteCarloTreeSearch<Tablut>>();
+		test_seed::<ParallelMcts<Tablut>>();
+		test_seed::<RandomAgent<Tablut>>();
+		let a = compete::<Tablut, RandomAgent<Tablut>, RandomAgent<Tablut>>(Duration::ZERO, 3);
+		let b = compete::<Tablut, RandomAgent<Tablut>, RandomAgent<Tablut>>(Duration::ZERO, 3);
+		assert_eq!(a.to_string(), b.to_string());
+	}
+
+	#[test]
+	fn test_rollout() {
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		let mut moves = Vec::new();
+		let start = Tablut::new(true);
+		// even at the start whatever the rules, an escape is a sure win
+		for rules in [Rules::ASHTON, Rules::SEABATTLE, Rules::BRANDUBH].iter() {
+			let g = Tablut::with_rules(true, *rules);
+			assert_eq!(DefaultHeuristic::win_probability(&g), 0.5);
+		}
+		let mut g: Tablut = "9/1K7/9/9/9/9/9/4A4/D8 w 0".parse().unwrap();
+		g.mov(&"b2-a2".parse().unwrap());
+		assert_eq!(DefaultHeuristic::win_probability(&g), 1.0);
+		for _ in 0..10 {
+			let mut g = start.clone();
+			let p = RandomRollout::play(&mut g, &mut moves, &mut rng);
+			assert!(g.state() != State::Going);
+			assert!(p == 0.0 || p == 0.5 || p == 1.0);
+			// cut short after 6 plies
+			let mut g = start.clone();
+			let p = HeuristicRollout::<DefaultHeuristic, 10, 6>::play(&mut g, &mut moves, &mut rng);
+			assert!(g.turn <= 6);
+			assert!((0.0..=1.0).contains(&p));
+			// without randomness the same line every time
+			let mut g = start.clone();
+			let a = HeuristicRollout::<DefaultHeuristic, 0, 6>::play(&mut g, &mut moves, &mut rng);
+			let mut g = start.clone();
+			let b = HeuristicRollout::<DefaultHeuristic, 0, 6>::play(&mut g, &mut moves, &mut rng);
+			assert_eq!(a, b);
+		}
+		let mut a =
+			MonteCarloTreeSearch::<Tablut, HeuristicRollout<DefaultHeuristic, 10, 8>>::new(true);
+		let m = a.think(Limits::nodes(128));
+		assert!(a.get_game().is_legal(&m));
+	}
+
+	// less time than the margin still gives a move
+	fn test_short<A: Ai<Tablut>>() {
+		let mut a = A::new(true);
+		let m = a.get_mov(Duration::from_millis(5));
+		assert!(a.get_game().is_legal(&m));
+		// the fields are public, depth 0 still searches the first ply
+		let depth0 = Limits {
+			depth: Some(0),
+			..Limits::default()
+		};
+		let m = A::new(true).think(depth0);
+		assert!(a.get_game().is_legal(&m));
+	}
+
+	#[test]
+	fn test_limits() {
+		test_short::<MinimaxSimple<Tablut, DefaultHeuristic>>();
+		test_short::<MinimaxFixed<Tablut, DefaultHeuristic, 8>>();
+		test_short::<MinimaxHard<Tablut, DefaultHeuristic>>();
+		test_short::<MinimaxKiller<Tablut, DefaultHeuristic>>();
+		test_short::<MinimaxKillerB<Tablut, DefaultHeuristic>>();
+		test_short::<MinimaxFinal<Tablut, DefaultHeuristic>>();
+		test_short::<Pvs<Tablut, DefaultHeuristic>>();
+		test_short::<LazySmp<Tablut, DefaultHeuristic>>();
+		test_short::<MonteCarloTreeSearch<Tablut>>();
+		test_short::<ParallelMcts<Tablut>>();
+		test_short::<MonteCarloTotal<Tablut>>();
+		// fixed depth searches end there and always agree
+		let (m, infos) = test_info::<Pvs<Tablut, DefaultHeuristic>>(Limits::depth(4));
+		assert_eq!(infos.last().unwrap().depth, 4);
+		let (m2, _) = test_info::<Pvs<Tablut, DefaultHeuristic>>(Limits::depth(4));
+		assert_eq!(m, m2);
+		let (_, infos) = test_info::<MinimaxFinal<Tablut, DefaultHeuristic>>(Limits::depth(3));
+		assert_eq!(infos.last().unwrap().depth, 3);
+		let (_, infos) = test_info::<MinimaxSimple<Tablut, DefaultHeuristic>>(Limits::depth(3));
+		assert_eq!(infos.last().unwrap().depth, 3);
+		// mcts checks its playouts every 32
+		let (_, infos) = test_info::<MonteCarloTreeSearch<Tablut>>(Limits::nodes(64));
+		assert_eq!(infos[0].nodes, 64);
+		let (_, infos) = test_info::<MinimaxKiller<Tablut, DefaultHeuristic>>(Limits::nodes(2000));
+		assert!(infos.last().unwrap().nodes < 2000);
+	}
+
+	#[test]
+	fn test_symmetry() {
+		let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+		for _ in 0..20 {
+			let mut g = Tablut::new(true);
+			while g.state() == State::Going {
+				let board = g.get_board();
+				let mut moves = g.get_moves();
+				moves.sort_unstable();
+				for s in Symmetry::ALL.iter() {
+					let mut tboard = [[Tile::E; 9]; 9];
+					for (y, row) in board.iter().enumerate() {
+						for (x, t) in row.iter().enumerate() {
+							let (tx, ty) = s.coords((x as u8, y as u8));
+							tboard[ty as usize][tx as usize] = *t;
+						}
+					}
+					let tg = Tablut::from_board(&tboard, g.turn);
+					assert_eq!(tg.get_canonical_hash(), g.get_canonical_hash());
+					assert_eq!(tg.canonical().1, g.canonical().1);
+					let mut tmoves: Vec<_> =
+						tg.get_moves().iter().map(|m| s.inverse().mov(*m)).collect();
+					tmoves.sort_unstable();
+					assert_eq!(tmoves, moves);
+				}
+				let m = *moves.choose(&mut rng).unwrap();
+				g.mov(&m);
+			}
+		}
 	}
 
 	#[test]
@@ -220,17 +1151,52 @@ mod tests {
 			Tablut,
 			MinimaxSimple<Tablut, DefaultHeuristic>,
 			MinimaxKillerB<Tablut, DefaultHeuristic>,
-		>(Duration::from_millis(1000));
+		>(Duration::from_millis(1000), 0);
 		// compete::<
 		// 	Tablut,
 		// 	MinimaxKillerB<Tablut, DefaultHeuristic>,
 		// 	MinimaxSimple<Tablut, DefaultHeuristic>,
-		// >(Duration::from_millis(1000));
+		// >(Duration::from_millis(1000), 0);
+	}
+
+	// a game of pvs against MinimaxKillerB, run with --ignored
+	#[test]
+	#[ignore]
+	fn test_pvs_compete() {
+		let record = compete::<
+			Tablut,
+			Pvs<Tablut, DefaultHeuristic>,
+			MinimaxKillerB<Tablut, DefaultHeuristic>,
+		>(Duration::from_millis(1000), 0);
+		assert!(record.replay().is_ok());
+	}
+
+	// what late move reductions and null moves are worth against
+	// MinimaxKillerB, with and without them. about ten minutes on one core,
+	// run with --release --ignored --nocapture
+	#[test]
+	#[ignore]
+	fn test_pruning_elo() {
+		let tl = Duration::from_millis(50);
+		let pruned = elo_match::<
+			Tablut,
+			Pvs<Tablut, DefaultHeuristic, 0, true, true>,
+			MinimaxKillerB<Tablut, DefaultHeuristic>,
+		>(300, tl, 0);
+		let plain = elo_match::<
+			Tablut,
+			Pvs<Tablut, DefaultHeuristic, 0, false, false>,
+			MinimaxKillerB<Tablut, DefaultHeuristic>,
+		>(300, tl, 0);
+		println!("pruned {:?}\nplain {:?}", pruned, plain);
 	}
 
 	#[test]
 	fn test_new_tablut() {
-		let mut rng = Xoroshiro128Plus::from_rng(rand::thread_rng()).unwrap();
+		// printed so a failure can be replayed
+		let seed = rand::random();
+		eprintln!("seed {}", seed);
+		let mut rng = Xoroshiro128Plus::seed_from_u64(seed);
 		for _ in 0..100000 {
 			let mut g = old_tablut::Tablut::new(true);
 			let mut g_new = Tablut::new(true);
@@ -251,13 +1217,7 @@ mod tests {
 				let mut moves_new: Vec<((u8, u8), (u8, u8))> = g_new
 					.get_moves()
 					.iter()
-					.map(|x| {
-						if x.0 == x.1 {
-							((0, 0), (0, 0))
-						} else {
-							((x.0 % 11 - 1, x.0 / 11 - 1), (x.1 % 11 - 1, x.1 / 11 - 1))
-						}
-					})
+					.map(|x| x.coords().unwrap_or(((0, 0), (0, 0))))
 					.collect();
 				moves.sort();
 				moves_new.sort();
@@ -291,9 +1251,9 @@ mod tests {
 				let m = moves.choose(&mut rng).unwrap();
 				let m0 = (m.0 .0 + m.0 .1 * 9, m.1 .0 + m.1 .1 * 9);
 				let m1 = if *m == ((0, 0), (0, 0)) {
-					(0, 0)
+					Move::NONE
 				} else {
-					(m.0 .0 + m.0 .1 * 11 + 12, m.1 .0 + m.1 .1 * 11 + 12)
+					Move::from_coords(m.0, m.1)
 				};
 				rb.push(g.mov_with_rollback(&m0));
 				rb_new.push(g_new.mov_with_rollback(&m1));
@@ -308,8 +1268,7 @@ mod tests {
 				eprintln!("g_new:\n{}", g_new);
 
 				eprintln!("rolling back");
-				while !rb.is_empty() {
-					let trb = rb.pop().unwrap();
+				while let Some(trb) = rb.pop() {
 					let trb_new = rb_new.pop().unwrap();
 					g.rollback(trb);
 					g_new.rollback(trb_new);
@@ -324,6 +1283,7 @@ mod tests {
 	// use test::Bencher;
 	// #[bench]
 	// fn bench_tablut(b: &mut Bencher) {
-	// 	b.iter(|| super::random_play::<Tablut>());
+	// 	let mut rng = Xoroshiro128Plus::seed_from_u64(0);
+	// 	b.iter(|| super::random_play::<Tablut>(&mut rng));
 	// }
 }
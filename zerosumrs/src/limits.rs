@@ -0,0 +1,62 @@
+use std::time::Duration;
+use std::time::Instant;
+
+// kept out of the move time to return the move before it runs out
+const MARGIN: Duration = Duration::from_millis(20);
+
+// when a search has to end, whichever limit is hit first. with none set it
+// runs until it is stopped, or until there is nothing deeper to search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+	// time for the whole move, margin included
+	pub time: Option<Duration>,
+	// last depth searched, the monte carlo engines count the length of their
+	// principal variation
+	pub depth: Option<u32>,
+	// positions searched, or playouts for the monte carlo engines. checked
+	// every few hundred nodes, so it can be passed by a little
+	pub nodes: Option<u64>,
+}
+
+impl Limits {
+	pub fn time(tl: Duration) -> Self {
+		Self {
+			time: Some(tl),
+			..Self::default()
+		}
+	}
+	pub fn depth(depth: u32) -> Self {
+		Self {
+			depth: Some(depth.max(1)),
+			..Self::default()
+		}
+	}
+	pub fn nodes(nodes: u64) -> Self {
+		Self {
+			nodes: Some(nodes),
+			..Self::default()
+		}
+	}
+	pub fn infinite() -> Self {
+		Self::default()
+	}
+	// how long the search itself may take
+	pub fn search_time(&self) -> Duration {
+		self.time
+			.map_or(Duration::MAX, |t| t.saturating_sub(MARGIN))
+	}
+	// a limit of 0 still allows the first ply, there has to be a move
+	pub fn allows_depth(&self, depth: u32) -> bool {
+		self.depth.is_none_or(|d| depth <= d.max(1))
+	}
+	// time or nodes are over for a search started at st
+	pub fn expired(&self, st: Instant, nodes: u64) -> bool {
+		self.nodes.is_some_and(|n| nodes >= n) || st.elapsed() > self.search_time()
+	}
+}
+
+impl From<Duration> for Limits {
+	fn from(tl: Duration) -> Self {
+		Self::time(tl)
+	}
+}
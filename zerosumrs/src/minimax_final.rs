@@ -1,9 +1,14 @@
 use crate::ai::Ai;
 use crate::game::*;
 use crate::heuristic::Heuristic;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::quiescence::Quiescence;
 use std::marker::PhantomData;
 use std::mem::take;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
 struct Tree<G: Game> {
@@ -26,18 +31,24 @@ impl<G: Game> Default for Tree<G> {
 	}
 }
 
-pub struct MinimaxFinal<G: Game, H: Heuristic<G>> {
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off
+pub struct MinimaxFinal<G: Game, H: Heuristic<G>, const Q: u32 = 0> {
 	pub g: G,
 	cur_depth: u32,
 	tree: Tree<G>,
 	nnw: u8,
 	st: Instant,
-	tl: Duration,
+	limits: Limits,
 	ended_early: bool,
+	stop: Arc<AtomicBool>,
+	nodes: u64,
+	reporter: Reporter<G::M>,
+	moves: Vec<G::M>,
+	q: Quiescence<G>,
 	_ph: PhantomData<H>,
 }
 
-impl<G: Game, H: Heuristic<G>> MinimaxFinal<G, H> {
+impl<G: Game, H: Heuristic<G>, const Q: u32> MinimaxFinal<G, H, Q> {
 	// assumes to be called with depth always increased by 1 relative to Tree
 	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32, t: &mut Tree<G>) {
 		// if win/loss is certain, no need to check again
@@ -47,24 +58,24 @@ impl<G: Game, H: Heuristic<G>> MinimaxFinal<G, H> {
 		}
 		if self.g.state() != State::Going || depth == 1 {
 			if t.depth == 0 {
-				t.val = H::eval(&self.g);
+				t.val = self.q.eval::<H>(&mut self.g, i64::MIN, i64::MAX, Q);
 			}
 			t.depth = depth;
 			return;
 		}
 		self.nnw = self.nnw.wrapping_add(1);
-		if self.nnw == 0 && self.st.elapsed() > self.tl {
+		self.nodes += 1;
+		if self.nnw == 0
+			&& (self.limits.expired(self.st, self.nodes + self.q.visited)
+				|| self.stop.load(Ordering::Relaxed))
+		{
 			self.ended_early = true;
 			return;
 		}
 
 		if t.children.is_empty() {
-			t.children = self
-				.g
-				.get_moves()
-				.iter()
-				.map(|x| (*x, Tree::<G>::new()))
-				.collect();
+			self.g.fill_moves(&mut self.moves);
+			t.children = self.moves.iter().map(|x| (*x, Tree::<G>::new())).collect();
 			t.children.shrink_to_fit();
 		} else if self.g.turn() {
 			t.children.sort_by_key(|x| (u32::MAX - x.1.depth, -x.1.val));
@@ -104,9 +115,55 @@ impl<G: Game, H: Heuristic<G>> MinimaxFinal<G, H> {
 			}
 		}
 	}
+	// deepens the stored tree until a limit is hit or the game is decided
+	fn deepen(&mut self) {
+		self.st = Instant::now();
+		self.ended_early = false;
+		self.nodes = 0;
+		self.q.reset_stats();
+		let mut t = take(&mut self.tree);
+		// tree depths count from 1, so cur_depth is the next depth to search
+		while t.val > -30000
+			&& t.val < 30000
+			&& !self.ended_early
+			&& self.limits.allows_depth(self.cur_depth)
+		{
+			self.cur_depth += 1;
+			self.minimax(i64::MIN, i64::MAX, self.cur_depth, &mut t);
+			if !self.ended_early {
+				self.report(&t);
+			}
+		}
+		if self.ended_early && self.cur_depth != 1 {
+			self.cur_depth -= 1;
+		}
+		self.tree = t;
+	}
+	// the line of best children, as far as the tree goes
+	fn report(&mut self, t: &Tree<G>) {
+		let mut pv = Vec::new();
+		let mut node = t;
+		let mut white = self.g.turn();
+		while let Some(c) = node
+			.children
+			.iter()
+			.filter(|c| c.1.depth != 0)
+			.max_by_key(|c| (c.1.depth, if white { c.1.val } else { -c.1.val }))
+		{
+			pv.push(c.0);
+			node = &c.1;
+			white = !white;
+		}
+		// tree depths count from 1
+		let depth = self.cur_depth - 1;
+		let nodes = self.nodes + self.q.visited;
+		let mut info = SearchInfo::new(depth, Some(t.val), nodes, self.st.elapsed(), pv);
+		info.seldepth = depth + self.q.max_ply;
+		self.reporter.report(&info);
+	}
 }
 
-impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxFinal<G, H> {
+impl<G: Game, H: Heuristic<G>, const Q: u32> Ai<G> for MinimaxFinal<G, H, Q> {
 	fn new(t: bool) -> Self {
 		Self {
 			g: G::new(t),
@@ -114,8 +171,13 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxFinal<G, H> {
 			cur_depth: 1,
 			nnw: 0,
 			st: Instant::now(),
-			tl: Duration::ZERO,
+			limits: Limits::default(),
 			ended_early: false,
+			stop: Arc::default(),
+			nodes: 0,
+			reporter: Reporter::default(),
+			moves: Vec::new(),
+			q: Quiescence::default(),
 			_ph: PhantomData,
 		}
 	}
@@ -133,30 +195,26 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxFinal<G, H> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, tl: Duration) -> G::M {
-		self.st = Instant::now();
-		self.tl = tl - Duration::from_millis(20);
-		self.ended_early = false;
+	fn think(&mut self, limits: Limits) -> G::M {
+		self.limits = limits;
+		self.deepen();
 		let mut t = take(&mut self.tree);
-		while t.val > -30000 && t.val < 30000 && !self.ended_early {
-			self.cur_depth += 1;
-			self.minimax(i64::MIN, i64::MAX, self.cur_depth, &mut t);
-		}
-		if self.ended_early && self.cur_depth != 1 {
-			self.cur_depth -= 1;
-		}
 		if self.g.turn() {
 			t.children.sort_by_key(|x| (u32::MAX - x.1.depth, -x.1.val));
 		} else {
 			t.children.sort_by_key(|x| (u32::MAX - x.1.depth, x.1.val));
 		}
-		let ans = t.children[0].0;
+		// stopped before a single root move was searched
+		let ans = match t.children.first() {
+			Some(c) => c.0,
+			None => self
+				.g
+				.get_moves_sorted()
+				.first()
+				.copied()
+				.unwrap_or_default(),
+		};
 		self.tree = t;
-		eprintln!(
-			"minimax_final depth {}, val {}",
-			self.cur_depth - 1,
-			self.tree.val
-		);
 		ans
 	}
 	fn mov(&mut self, m: &G::M) {
@@ -172,4 +230,21 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxFinal<G, H> {
 		}
 		self.g.mov(m);
 	}
+	// mov keeps the subtree of the reply, already searched this deep
+	fn ponder(&mut self, stop: Arc<AtomicBool>) {
+		if self.g.state() != State::Going {
+			return;
+		}
+		// the search answers to the ponder flag meanwhile
+		let handle = std::mem::replace(&mut self.stop, stop);
+		self.limits = Limits::infinite();
+		self.deepen();
+		self.stop = handle;
+	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
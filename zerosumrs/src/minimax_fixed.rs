@@ -1,20 +1,47 @@
 use crate::ai::Ai;
 use crate::game::*;
 use crate::heuristic::Heuristic;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::quiescence::Quiescence;
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
 
-pub struct MinimaxFixed<G: Game, H: Heuristic<G>, const D: u32> {
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off
+pub struct MinimaxFixed<G: Game, H: Heuristic<G>, const D: u32, const Q: u32 = 0> {
 	pub g: G,
+	nnw: u8,
+	ended_early: bool,
+	limits: Limits,
+	stop: Arc<AtomicBool>,
+	st: Instant,
+	nodes: u64,
+	reporter: Reporter<G::M>,
+	moves: MoveBuffers<G::M>,
+	q: Quiescence<G>,
 	_ph: PhantomData<H>,
 }
 
-impl<G: Game, H: Heuristic<G>, const D: u32> MinimaxFixed<G, H, D> {
+impl<G: Game, H: Heuristic<G>, const D: u32, const Q: u32> MinimaxFixed<G, H, D, Q> {
 	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32) -> i64 {
 		if self.g.state() != State::Going || depth == 0 {
-			return H::eval(&self.g);
+			return self.q.eval::<H>(&mut self.g, a, b, Q);
 		}
-		let moves = self.g.get_moves_sorted();
+		self.nodes += 1;
+		self.nnw = self.nnw.wrapping_add(1);
+		if self.ended_early
+			|| (self.nnw == 0
+				&& (self.limits.expired(self.st, self.nodes + self.q.visited)
+					|| self.stop.load(Ordering::Relaxed)))
+		{
+			self.ended_early = true;
+			return if self.g.turn() { a } else { b };
+		}
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves_sorted(&mut moves);
 		for m in moves.iter() {
 			let rb = self.g.mov_with_rollback(m);
 			let h = self.minimax(a, b, depth - 1);
@@ -24,25 +51,32 @@ impl<G: Game, H: Heuristic<G>, const D: u32> MinimaxFixed<G, H, D> {
 			} else {
 				b = b.min(h);
 			}
-			if a >= b {
+			if a >= b || self.ended_early {
 				break;
 			}
 		}
+		self.moves.put(depth, moves);
 		if self.g.turn() {
 			a
 		} else {
 			b
 		}
 	}
-	fn minimax_move(&mut self, depth: u32) -> G::M {
+	// best move and its value, of the moves searched before a stop
+	fn minimax_move(&mut self, depth: u32) -> (G::M, i64) {
+		self.ended_early = false;
 		let mut a = i64::MIN;
 		let mut b = i64::MAX;
-		let moves = self.g.get_moves_sorted();
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves_sorted(&mut moves);
 		let mut ans = moves[0];
 		for m in moves.iter() {
 			let rb = self.g.mov_with_rollback(m);
 			let h = self.minimax(a, b, depth - 1);
 			self.g.rollback(rb);
+			if self.ended_early {
+				break;
+			}
 			if self.g.turn() {
 				if h > a {
 					a = h;
@@ -58,14 +92,24 @@ impl<G: Game, H: Heuristic<G>, const D: u32> MinimaxFixed<G, H, D> {
 				break;
 			}
 		}
-		ans
+		self.moves.put(depth, moves);
+		(ans, if self.g.turn() { a } else { b })
 	}
 }
 
-impl<G: Game, H: Heuristic<G>, const D: u32> Ai<G> for MinimaxFixed<G, H, D> {
+impl<G: Game, H: Heuristic<G>, const D: u32, const Q: u32> Ai<G> for MinimaxFixed<G, H, D, Q> {
 	fn new(t: bool) -> Self {
 		Self {
 			g: G::new(t),
+			nnw: 0,
+			ended_early: false,
+			limits: Limits::default(),
+			stop: Arc::default(),
+			st: Instant::now(),
+			nodes: 0,
+			reporter: Reporter::default(),
+			moves: MoveBuffers::default(),
+			q: Quiescence::default(),
 			_ph: PhantomData,
 		}
 	}
@@ -82,11 +126,29 @@ impl<G: Game, H: Heuristic<G>, const D: u32> Ai<G> for MinimaxFixed<G, H, D> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, _tl: Duration) -> G::M {
-		eprintln!("minimax_fixed depth {}", D);
-		self.minimax_move(D)
+	// searches to D unless the limits ask for a depth, time or nodes cut it
+	// short with the best move found so far
+	fn think(&mut self, limits: Limits) -> G::M {
+		self.limits = limits;
+		self.st = Instant::now();
+		let depth = limits.depth.unwrap_or(D).max(1);
+		self.nodes = 0;
+		self.q.reset_stats();
+		let (ans, val) = self.minimax_move(depth);
+		let nodes = self.nodes + self.q.visited;
+		let elapsed = self.st.elapsed();
+		let mut info = SearchInfo::new(depth, Some(val), nodes, elapsed, vec![ans]);
+		info.seldepth = depth + self.q.max_ply;
+		self.reporter.report(&info);
+		ans
 	}
 	fn mov(&mut self, m: &G::M) {
 		self.g.mov(m);
 	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
@@ -1,97 +1,76 @@
 use crate::ai::Ai;
 use crate::game::*;
 use crate::heuristic::Heuristic;
-use std::collections::HashMap;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::quiescence::Quiescence;
+use crate::tt::*;
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
-pub struct MinimaxHard<G: Game, H: Heuristic<G>> {
+// iterative deepening gives up here, only reached in decided positions
+const MAX_DEPTH: u32 = 64;
+
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off
+pub struct MinimaxHard<G: Game, H: Heuristic<G>, const Q: u32 = 0> {
 	pub g: G,
-	table: HashMap<G::S, (i64, u32)>,
+	nnw: u8,
+	ended_early: bool,
+	stop: Arc<AtomicBool>,
+	nodes: u64,
+	reporter: Reporter<G::M>,
+	table: TranspositionTable<G::M>,
+	moves: MoveBuffers<G::M>,
+	q: Quiescence<G>,
 	_ph: PhantomData<H>,
 }
 
-impl<G: Game, H: Heuristic<G>> MinimaxHard<G, H> {
-	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32) -> i64 {
-		if self.g.state() != State::Going || depth == 0 {
-			return H::eval(&self.g);
+impl<G: Game, H: Heuristic<G>, const Q: u32> MinimaxHard<G, H, Q> {
+	// value of the position and the best move found
+	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32) -> (i64, G::M) {
+		self.nodes += 1;
+		self.nnw = self.nnw.wrapping_add(1);
+		if self.ended_early || (self.nnw == 0 && self.stop.load(Ordering::Relaxed)) {
+			self.ended_early = true;
+			return (if self.g.turn() { a } else { b }, G::M::default());
 		}
-		let mut old_depth = 0;
-		if let Some(x) = self.table.get(&self.g.get_static_state()) {
-			if depth <= x.1 {
-				return x.0;
+		let (a0, b0) = (a, b);
+		let key = self.g.get_hash();
+		let mut hash_mov = None;
+		if let Some(e) = self.table.probe(key) {
+			if let Some(v) = e.cutoff(depth, a, b) {
+				return (v, e.mov);
 			}
-			old_depth = x.1;
+			hash_mov = Some(e.mov);
 		}
 		let mut res = if self.g.turn() { a } else { b };
-		let mut moves = self.g.get_moves();
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves(&mut moves);
+		let mut ans = moves[0];
+		// children already searched first, then the stored best move before all
 		moves.sort_by_cached_key(|m| {
 			let rb = self.g.mov_with_rollback(m);
-			let ans = self
-				.table
-				.get(&self.g.get_static_state())
-				.unwrap_or(&(res, 0))
-				.0;
+			let val = self.table.probe(self.g.get_hash()).map_or(res, |e| e.val);
 			self.g.rollback(rb);
 			if self.g.turn() {
-				-ans
+				val.saturating_neg()
 			} else {
-				ans
+				val
 			}
 		});
+		if let Some(i) = hash_mov.and_then(|hm| moves.iter().position(|m| *m == hm)) {
+			moves[..=i].rotate_right(1);
+		}
 		for m in moves.iter() {
 			let rb = self.g.mov_with_rollback(m);
-			let h = self.minimax(a, b, depth - 1);
+			let h = self.eval(a, b, depth - 1);
 			self.g.rollback(rb);
-			if self.g.turn() {
-				res = res.max(h);
-				a = a.max(h);
-			} else {
-				res = res.min(h);
-				b = b.min(h);
-			}
-			if a >= b {
+			if self.ended_early {
 				break;
 			}
-		}
-		if depth > old_depth {
-			self.table.insert(self.g.get_static_state(), (res, depth));
-		}
-		res
-	}
-	fn minimax_move(&mut self, depth: u32) -> G::M {
-		if self.g.state() != State::Going || depth == 0 {
-			panic!();
-		}
-		let mut a = i64::MIN;
-		let mut b = i64::MAX;
-		let mut old_depth = 0;
-		if let Some(x) = self.table.get(&self.g.get_static_state()) {
-			old_depth = x.1;
-		}
-		let mut res = if self.g.turn() { a } else { b };
-
-		let mut moves = self.g.get_moves();
-		let mut ans = moves[0];
-		moves.sort_by_cached_key(|m| {
-			let rb = self.g.mov_with_rollback(m);
-			let ans = self
-				.table
-				.get(&self.g.get_static_state())
-				.unwrap_or(&(res, 0))
-				.0;
-			self.g.rollback(rb);
-			if self.g.turn() {
-				-ans
-			} else {
-				ans
-			}
-		});
-		for m in moves.iter() {
-			let rb = self.g.mov_with_rollback(m);
-			let h = self.minimax(a, b, depth - 1);
-			self.g.rollback(rb);
 			if self.g.turn() {
 				if h > res {
 					res = h;
@@ -109,20 +88,50 @@ impl<G: Game, H: Heuristic<G>> MinimaxHard<G, H> {
 				break;
 			}
 		}
-		if depth > old_depth
-		/*&& depth > 4*/
-		{
-			self.table.insert(self.g.get_static_state(), (res, depth));
+		self.moves.put(depth, moves);
+		if !self.ended_early {
+			self.table
+				.store(key, res, depth, Bound::of(res, a0, b0), ans);
 		}
-		ans
+		(res, ans)
+	}
+	fn eval(&mut self, a: i64, b: i64, depth: u32) -> i64 {
+		if self.g.state() != State::Going || depth == 0 {
+			return self.q.eval::<H>(&mut self.g, a, b, Q);
+		}
+		self.minimax(a, b, depth).0
+	}
+	// None if the search was stopped
+	fn minimax_move(&mut self, depth: u32, st: Instant) -> Option<G::M> {
+		if self.g.state() != State::Going || depth == 0 {
+			panic!();
+		}
+		let (val, ans) = self.minimax(i64::MIN, i64::MAX, depth);
+		if self.ended_early {
+			return None;
+		}
+		let table = &self.table;
+		let pv = principal_variation(&self.g, depth, |k| table.probe(k).map(|e| e.mov));
+		let nodes = self.nodes + self.q.visited;
+		let mut info = SearchInfo::new(depth, Some(val), nodes, st.elapsed(), pv);
+		info.seldepth = depth + self.q.max_ply;
+		self.reporter.report(&info);
+		Some(ans)
 	}
 }
 
-impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxHard<G, H> {
+impl<G: Game, H: Heuristic<G>, const Q: u32> Ai<G> for MinimaxHard<G, H, Q> {
 	fn new(t: bool) -> Self {
 		Self {
 			g: G::new(t),
-			table: HashMap::new(),
+			nnw: 0,
+			ended_early: false,
+			stop: Arc::default(),
+			nodes: 0,
+			reporter: Reporter::default(),
+			table: TranspositionTable::new(18),
+			moves: MoveBuffers::default(),
+			q: Quiescence::default(),
 			_ph: PhantomData,
 		}
 	}
@@ -139,20 +148,40 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxHard<G, H> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, tl: Duration) -> G::M {
+	// a depth is never cut short, the next one is only started if it looks
+	// like it fits in the time and the nodes left
+	fn think(&mut self, limits: Limits) -> G::M {
 		let start_time = Instant::now();
-		let mut depth = 1;
-		let mut ans = self.minimax_move(1);
-		loop {
-			if start_time.elapsed() * 20 > tl {
+		self.table.new_search();
+		self.nodes = 0;
+		self.q.reset_stats();
+		self.ended_early = false;
+		let mut ans = self.g.get_moves_sorted()[0];
+		let mut depth = 0;
+		while limits.allows_depth(depth + 1) && depth < MAX_DEPTH {
+			// each depth takes about 20 times the one before
+			let nodes = (self.nodes + self.q.visited).saturating_mul(20);
+			if depth > 0
+				&& (limits.nodes.is_some_and(|n| nodes > n)
+					|| start_time.elapsed() * 20 > limits.search_time())
+			{
 				break;
 			}
 			depth += 1;
-			ans = self.minimax_move(depth);
+			match self.minimax_move(depth, start_time) {
+				Some(m) => ans = m,
+				None => break,
+			}
 		}
 		ans
 	}
 	fn mov(&mut self, m: &G::M) {
 		self.g.mov(m);
 	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
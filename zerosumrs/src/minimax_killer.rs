@@ -1,39 +1,75 @@
 use crate::ai::Ai;
 use crate::game::*;
 use crate::heuristic::Heuristic;
-use std::collections::HashMap;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::ordering::MoveOrdering;
+use crate::quiescence::Quiescence;
+use crate::tt::*;
 use std::collections::VecDeque;
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
-pub struct MinimaxKiller<G: Game, H: Heuristic<G>> {
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off
+pub struct MinimaxKiller<G: Game, H: Heuristic<G>, const Q: u32 = 0> {
 	pub g: G,
 	nnw: u8,
-	tl: Duration,
+	limits: Limits,
+	stop: Arc<AtomicBool>,
 	st: Instant,
-	cache: HashMap<G::S, i64>,
+	table: TranspositionTable<G::M>,
 	best_mov: VecDeque<G::M>,
 	ended_early: bool,
 	cur_depth: u32,
+	nodes: u64,
+	reporter: Reporter<G::M>,
+	moves: MoveBuffers<G::M>,
+	ordering: MoveOrdering<G>,
+	q: Quiescence<G>,
 	_ph: PhantomData<H>,
 }
 
-impl<G: Game, H: Heuristic<G>> MinimaxKiller<G, H> {
-	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32) -> i64 {
+impl<G: Game, H: Heuristic<G>, const Q: u32> MinimaxKiller<G, H, Q> {
+	// prev is the move that led here
+	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32, prev: G::M) -> i64 {
 		if self.g.state() != State::Going || depth == 0 {
-			return H::eval(&self.g);
+			return self.q.eval::<H>(&mut self.g, a, b, Q);
 		}
 		self.nnw = self.nnw.wrapping_add(1);
-		if self.ended_early || (self.nnw == 0 && self.st.elapsed() > self.tl) {
+		self.nodes += 1;
+		if self.ended_early
+			|| (self.nnw == 0
+				&& (self.limits.expired(self.st, self.nodes + self.q.visited)
+					|| self.stop.load(Ordering::Relaxed)))
+		{
 			self.ended_early = true;
 			return if self.g.turn() { a } else { b };
 		}
-		let moves = self.g.get_moves();
-		let bm = self.best_mov[depth as usize];
+		let (a0, b0) = (a, b);
+		let key = self.g.get_hash();
+		let mut bm = self.best_mov[depth as usize];
+		// the root has to set best_mov itself
+		if depth < self.cur_depth {
+			if let Some(e) = self.table.probe(key) {
+				if let Some(v) = e.cutoff(depth, a, b) {
+					return v;
+				}
+				bm = e.mov;
+			}
+		}
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves(&mut moves);
+		let white = self.g.turn();
+		let ply = (self.cur_depth - depth) as usize;
+		self.ordering.order(&mut moves, Some(bm), ply, white, &prev);
+		let mut best = moves[0];
 		if moves.contains(&bm) {
+			best = bm;
 			let rb = self.g.mov_with_rollback(&bm);
-			let h = self.minimax(a, b, depth - 1);
+			let h = self.minimax(a, b, depth - 1, bm);
 			self.g.rollback(rb);
 			if self.g.turn() {
 				a = a.max(h);
@@ -41,6 +77,7 @@ impl<G: Game, H: Heuristic<G>> MinimaxKiller<G, H> {
 				b = b.min(h);
 			}
 			if self.ended_early {
+				self.moves.put(depth, moves);
 				return if self.g.turn() { a } else { b };
 			}
 		}
@@ -49,43 +86,68 @@ impl<G: Game, H: Heuristic<G>> MinimaxKiller<G, H> {
 				continue;
 			}
 			let rb = self.g.mov_with_rollback(m);
-			let h = self.minimax(a, b, depth - 1);
+			let h = self.minimax(a, b, depth - 1, *m);
 			self.g.rollback(rb);
 			if self.g.turn() {
 				if h > a {
 					a = h;
+					best = *m;
 					if !self.ended_early {
 						self.best_mov[depth as usize] = *m;
 					}
 				}
 			} else if h < b {
 				b = h;
+				best = *m;
 				if !self.ended_early {
 					self.best_mov[depth as usize] = *m;
 				}
 			}
+			if a >= b && !self.ended_early {
+				self.ordering.cutoff(m, ply, white, &prev, depth);
+			}
 			if a >= b || self.ended_early {
 				break;
 			}
 		}
+		self.moves.put(depth, moves);
 		let res = if self.g.turn() { a } else { b };
+		if !self.ended_early {
+			self.table
+				.store(key, res, depth, Bound::of(res, a0, b0), best);
+		}
 		res
 	}
+	fn report(&mut self, val: i64) {
+		let depth = self.cur_depth;
+		let table = &self.table;
+		let pv = principal_variation(&self.g, depth, |k| table.probe(k).map(|e| e.mov));
+		let nodes = self.nodes + self.q.visited;
+		let mut info = SearchInfo::new(depth, Some(val), nodes, self.st.elapsed(), pv);
+		info.seldepth = depth + self.q.max_ply;
+		self.reporter.report(&info);
+	}
 }
 
-impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxKiller<G, H> {
+impl<G: Game, H: Heuristic<G>, const Q: u32> Ai<G> for MinimaxKiller<G, H, Q> {
 	fn new(t: bool) -> Self {
 		let mut vd = VecDeque::with_capacity(8);
 		vd.push_back(G::M::default());
 		Self {
 			g: G::new(t),
 			nnw: 0,
-			tl: Duration::ZERO,
-			cache: HashMap::new(),
+			limits: Limits::default(),
+			stop: Arc::default(),
+			table: TranspositionTable::new(18),
 			st: Instant::now(),
 			best_mov: vd,
 			ended_early: false,
 			cur_depth: 0,
+			nodes: 0,
+			reporter: Reporter::default(),
+			moves: MoveBuffers::default(),
+			ordering: MoveOrdering::default(),
+			q: Quiescence::default(),
 			_ph: PhantomData,
 		}
 	}
@@ -103,19 +165,39 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxKiller<G, H> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, tl: Duration) -> G::M {
-		self.tl = tl - Duration::from_millis(20);
+	fn think(&mut self, limits: Limits) -> G::M {
+		self.limits = limits;
 		self.st = Instant::now();
 		self.ended_early = false;
-		while !self.ended_early {
+		self.table.new_search();
+		self.ordering.new_search();
+		self.nodes = 0;
+		self.q.reset_stats();
+		while !self.ended_early && self.limits.allows_depth(self.cur_depth + 1) {
 			self.cur_depth += 1;
 			self.best_mov.push_front(self.best_mov[0]);
-			self.minimax(i64::MIN, i64::MAX, self.cur_depth);
+			let val = self.minimax(i64::MIN, i64::MAX, self.cur_depth, G::M::default());
+			if !self.ended_early {
+				self.report(val);
+			}
+		}
+		// the last depth was cut short
+		if self.ended_early {
+			self.cur_depth -= 1;
+			self.best_mov.pop_front();
+		}
+		// the move can be left over from an earlier position when no depth
+		// was searched, stopped or past the depth limit
+		let m = *self.best_mov.back().unwrap();
+		if self.g.is_legal(&m) {
+			m
+		} else {
+			self.g
+				.get_moves_sorted()
+				.first()
+				.copied()
+				.unwrap_or_default()
 		}
-		self.cur_depth -= 1;
-		self.best_mov.pop_front();
-		eprintln!("minimax_killer depth {}", self.cur_depth);
-		*self.best_mov.back().unwrap()
 	}
 	fn mov(&mut self, m: &G::M) {
 		self.g.mov(m);
@@ -124,4 +206,10 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxKiller<G, H> {
 			self.best_mov.pop_back();
 		}
 	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
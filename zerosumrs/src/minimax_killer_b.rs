@@ -1,65 +1,91 @@
 use crate::ai::Ai;
 use crate::game::*;
 use crate::heuristic::Heuristic;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::ordering::MoveOrdering;
+use crate::quiescence::Quiescence;
 use std::collections::VecDeque;
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
-pub struct MinimaxKillerB<G: Game, H: Heuristic<G>> {
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off
+pub struct MinimaxKillerB<G: Game, H: Heuristic<G>, const Q: u32 = 0> {
 	pub g: G,
 	nnw: u8,
-	tl: Duration,
+	limits: Limits,
+	stop: Arc<AtomicBool>,
 	st: Instant,
 	best_mov: VecDeque<G::M>,
 	global_best: VecDeque<G::M>,
+	// line found by the node being searched at each depth, indexed by depth
+	// like best_mov
+	pv: Vec<Vec<G::M>>,
 	ended_early: bool,
 	cur_depth: u32,
+	reporter: Reporter<G::M>,
+	moves: MoveBuffers<G::M>,
+	ordering: MoveOrdering<G>,
+	q: Quiescence<G>,
 	_ph: PhantomData<H>,
 	iterations: u64,
 }
 
-impl<G: Game, H: Heuristic<G>> MinimaxKillerB<G, H> {
-	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32, best: bool) -> (i64, VecDeque<G::M>) {
+impl<G: Game, H: Heuristic<G>, const Q: u32> MinimaxKillerB<G, H, Q> {
+	// prev is the move that led here
+	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32, best: bool, prev: G::M) -> i64 {
 		self.iterations += 1;
-		let mut mv = VecDeque::with_capacity(self.cur_depth as usize + 1);
-		for _ in 0..depth + 1 {
-			mv.push_back(G::M::default());
+		if self.pv.len() <= depth as usize {
+			self.pv.resize_with(depth as usize + 1, Vec::new);
 		}
+		let line = &mut self.pv[depth as usize];
+		line.clear();
+		line.resize(depth as usize + 1, G::M::default());
 		if self.g.state() != State::Going || depth == 0 {
-			return (H::eval(&self.g), mv);
+			return self.q.eval::<H>(&mut self.g, a, b, Q);
 		}
 		self.nnw = self.nnw.wrapping_add(1);
-		if self.ended_early || (self.nnw == 0 && self.st.elapsed() > self.tl) {
+		if self.ended_early
+			|| (self.nnw == 0
+				&& (self
+					.limits
+					.expired(self.st, self.iterations + self.q.visited)
+					|| self.stop.load(Ordering::Relaxed)))
+		{
 			self.ended_early = true;
-			return (if self.g.turn() { a } else { b }, mv);
+			return if self.g.turn() { a } else { b };
 		}
 
-		let moves = self.g.get_moves();
 		let bm = if best {
 			self.global_best[depth as usize]
 		} else {
 			self.best_mov[depth as usize]
 		};
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves(&mut moves);
+		let white = self.g.turn();
+		let ply = (self.cur_depth - depth) as usize;
+		self.ordering.order(&mut moves, Some(bm), ply, white, &prev);
 		if moves.contains(&bm) {
 			let rb = self.g.mov_with_rollback(&bm);
-			let (h, hv) = self.minimax(a, b, depth - 1, best);
+			let h = self.minimax(a, b, depth - 1, best, bm);
 			self.g.rollback(rb);
 			if self.g.turn() {
 				if h > a {
 					a = h;
 					if !self.ended_early {
 						self.best_mov[depth as usize] = bm;
-						mv = hv;
-						mv.push_back(bm);
+						self.extend_pv(depth, bm);
 					}
 				}
 			} else if h < b {
 				b = h;
 				if !self.ended_early {
 					self.best_mov[depth as usize] = bm;
-					mv = hv;
-					mv.push_back(bm);
+					self.extend_pv(depth, bm);
 				}
 			}
 		}
@@ -69,50 +95,81 @@ impl<G: Game, H: Heuristic<G>> MinimaxKillerB<G, H> {
 				continue;
 			}
 			let rb = self.g.mov_with_rollback(m);
-			let (h, hv) = self.minimax(a, b, depth - 1, false);
+			let h = self.minimax(a, b, depth - 1, false, *m);
 			self.g.rollback(rb);
 			if self.g.turn() {
 				if h > a {
 					a = h;
 					if !self.ended_early {
 						self.best_mov[depth as usize] = *m;
-						mv = hv;
-						mv.push_back(*m);
+						self.extend_pv(depth, *m);
 					}
 				}
 			} else if h < b {
 				b = h;
 				if !self.ended_early {
 					self.best_mov[depth as usize] = *m;
-					mv = hv;
-					mv.push_back(*m);
+					self.extend_pv(depth, *m);
 				}
 			}
+			if a >= b && !self.ended_early {
+				self.ordering.cutoff(m, ply, white, &prev, depth);
+			}
 			if a >= b || self.ended_early {
 				break;
 			}
 		}
+		self.moves.put(depth, moves);
 		if self.g.turn() {
-			(a, mv)
+			a
 		} else {
-			(b, mv)
+			b
 		}
 	}
+	// m at depth, then the line of the child just searched
+	fn extend_pv(&mut self, depth: u32, m: G::M) {
+		let d = depth as usize;
+		let (child, line) = self.pv.split_at_mut(d);
+		line[0][..d].copy_from_slice(&child[d - 1]);
+		line[0][d] = m;
+	}
+	fn report(&mut self, val: i64) {
+		let depth = self.cur_depth;
+		// global_best ends with the root move
+		let pv = self
+			.global_best
+			.iter()
+			.rev()
+			.take(depth as usize)
+			.take_while(|m| **m != G::M::default())
+			.copied()
+			.collect();
+		let nodes = self.iterations + self.q.visited;
+		let mut info = SearchInfo::new(depth, Some(val), nodes, self.st.elapsed(), pv);
+		info.seldepth = depth + self.q.max_ply;
+		self.reporter.report(&info);
+	}
 }
 
-impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxKillerB<G, H> {
+impl<G: Game, H: Heuristic<G>, const Q: u32> Ai<G> for MinimaxKillerB<G, H, Q> {
 	fn new(t: bool) -> Self {
 		let mut vd = VecDeque::with_capacity(8);
 		vd.push_back(G::M::default());
 		Self {
 			g: G::new(t),
 			nnw: 0,
-			tl: Duration::ZERO,
+			limits: Limits::default(),
+			stop: Arc::default(),
 			st: Instant::now(),
 			best_mov: vd.clone(),
 			global_best: vd,
+			pv: Vec::new(),
 			ended_early: false,
 			cur_depth: 0,
+			reporter: Reporter::default(),
+			moves: MoveBuffers::default(),
+			ordering: MoveOrdering::default(),
+			q: Quiescence::default(),
 			_ph: PhantomData,
 			iterations: 0,
 		}
@@ -131,30 +188,44 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxKillerB<G, H> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, tl: Duration) -> G::M {
-		self.tl = tl - Duration::from_millis(20);
+	fn think(&mut self, limits: Limits) -> G::M {
+		self.limits = limits;
 		self.iterations = 0;
+		self.ordering.new_search();
+		self.q.reset_stats();
 		self.st = Instant::now();
 		self.ended_early = false;
-		let mut val = 0;
-		while !self.ended_early {
+		while !self.ended_early && self.limits.allows_depth(self.cur_depth + 1) {
 			self.cur_depth += 1;
 			self.best_mov.push_front(self.best_mov[0]);
 			self.global_best.push_front(self.global_best[0]);
-			let (h, hv) = self.minimax(i64::MIN, i64::MAX, self.cur_depth, true);
+			let root = self.cur_depth;
+			let h = self.minimax(i64::MIN, i64::MAX, root, true, G::M::default());
 			if !self.ended_early {
-				self.global_best = hv;
-				val = h;
+				self.global_best.clear();
+				self.global_best
+					.extend(self.pv[root as usize].iter().copied());
+				self.report(h);
 			}
 		}
-		self.cur_depth -= 1;
-		self.best_mov.pop_front();
-		self.global_best.pop_front();
-		eprintln!(
-			"minimax_killer_b depth {} val {} it {}",
-			self.cur_depth, val, self.iterations
-		);
-		*self.best_mov.back().unwrap()
+		// the last depth was cut short
+		if self.ended_early {
+			self.cur_depth -= 1;
+			self.best_mov.pop_front();
+			self.global_best.pop_front();
+		}
+		// the move can be left over from an earlier position when no depth
+		// was searched, stopped or past the depth limit
+		let m = *self.best_mov.back().unwrap();
+		if self.g.is_legal(&m) {
+			m
+		} else {
+			self.g
+				.get_moves_sorted()
+				.first()
+				.copied()
+				.unwrap_or_default()
+		}
 	}
 	fn mov(&mut self, m: &G::M) {
 		self.g.mov(m);
@@ -164,4 +235,10 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxKillerB<G, H> {
 			self.global_best.pop_back();
 		}
 	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
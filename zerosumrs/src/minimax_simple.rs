@@ -1,31 +1,49 @@
 use crate::ai::Ai;
 use crate::game::*;
 use crate::heuristic::Heuristic;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::quiescence::Quiescence;
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
-pub struct MinimaxSimple<G: Game, H: Heuristic<G>> {
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off
+pub struct MinimaxSimple<G: Game, H: Heuristic<G>, const Q: u32 = 0> {
 	pub g: G,
 	nnw: u8,
-	tl: Duration,
+	limits: Limits,
+	stop: Arc<AtomicBool>,
 	st: Instant,
 	last_ans: G::M,
+	last_val: i64,
 	ended_early: bool,
+	nodes: u64,
+	reporter: Reporter<G::M>,
+	moves: MoveBuffers<G::M>,
+	q: Quiescence<G>,
 	_ph: PhantomData<H>,
 }
 
-impl<G: Game, H: Heuristic<G>> MinimaxSimple<G, H> {
+impl<G: Game, H: Heuristic<G>, const Q: u32> MinimaxSimple<G, H, Q> {
 	fn minimax(&mut self, mut a: i64, mut b: i64, depth: u32) -> i64 {
 		if self.g.state() != State::Going || depth == 0 {
-			return H::eval(&self.g);
+			return self.q.eval::<H>(&mut self.g, a, b, Q);
 		}
 		self.nnw = self.nnw.wrapping_add(1);
-		if self.ended_early || (self.nnw == 0 && self.st.elapsed() > self.tl) {
+		self.nodes += 1;
+		if self.ended_early
+			|| (self.nnw == 0
+				&& (self.limits.expired(self.st, self.nodes + self.q.visited)
+					|| self.stop.load(Ordering::Relaxed)))
+		{
 			self.ended_early = true;
 			return if self.g.turn() { a } else { b };
 		}
-		let moves = self.g.get_moves_sorted();
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves_sorted(&mut moves);
 		for m in moves.iter() {
 			let rb = self.g.mov_with_rollback(m);
 			let h = self.minimax(a, b, depth - 1);
@@ -39,6 +57,7 @@ impl<G: Game, H: Heuristic<G>> MinimaxSimple<G, H> {
 				break;
 			}
 		}
+		self.moves.put(depth, moves);
 		if self.g.turn() {
 			a
 		} else {
@@ -48,7 +67,8 @@ impl<G: Game, H: Heuristic<G>> MinimaxSimple<G, H> {
 	fn minimax_move(&mut self, depth: u32) -> bool {
 		let mut a = i64::MIN;
 		let mut b = i64::MAX;
-		let moves = self.g.get_moves_sorted();
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves_sorted(&mut moves);
 		let mut ans = moves[0];
 		for m in moves.iter() {
 			let rb = self.g.mov_with_rollback(m);
@@ -69,24 +89,39 @@ impl<G: Game, H: Heuristic<G>> MinimaxSimple<G, H> {
 				break;
 			}
 		}
+		self.moves.put(depth, moves);
 		if self.ended_early {
 			true
 		} else {
 			self.last_ans = ans;
+			self.last_val = if self.g.turn() { a } else { b };
 			false
 		}
 	}
+	fn report(&mut self, depth: u32) {
+		let nodes = self.nodes + self.q.visited;
+		let pv = vec![self.last_ans];
+		let mut info = SearchInfo::new(depth, Some(self.last_val), nodes, self.st.elapsed(), pv);
+		info.seldepth = depth + self.q.max_ply;
+		self.reporter.report(&info);
+	}
 }
 
-impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxSimple<G, H> {
+impl<G: Game, H: Heuristic<G>, const Q: u32> Ai<G> for MinimaxSimple<G, H, Q> {
 	fn new(t: bool) -> Self {
 		Self {
 			g: G::new(t),
 			nnw: 0,
-			tl: Duration::ZERO,
+			limits: Limits::default(),
+			stop: Arc::default(),
 			st: Instant::now(),
 			last_ans: G::M::default(),
+			last_val: 0,
 			ended_early: false,
+			nodes: 0,
+			reporter: Reporter::default(),
+			moves: MoveBuffers::default(),
+			q: Quiescence::default(),
 			_ph: PhantomData,
 		}
 	}
@@ -104,18 +139,33 @@ impl<G: Game, H: Heuristic<G>> Ai<G> for MinimaxSimple<G, H> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, tl: Duration) -> G::M {
+	fn think(&mut self, limits: Limits) -> G::M {
 		let mut depth = 1;
-		self.tl = tl - Duration::from_millis(20);
+		self.limits = limits;
 		self.st = Instant::now();
 		self.ended_early = false;
-		while !self.minimax_move(depth) {
+		self.nodes = 0;
+		self.q.reset_stats();
+		// played if not even the first depth is finished
+		self.last_ans = self
+			.g
+			.get_moves_sorted()
+			.first()
+			.copied()
+			.unwrap_or_default();
+		while self.limits.allows_depth(depth) && !self.minimax_move(depth) {
+			self.report(depth);
 			depth += 1;
 		}
-		eprintln!("minimax_simple depth {}", depth - 1);
 		self.last_ans
 	}
 	fn mov(&mut self, m: &G::M) {
 		self.g.mov(m);
 	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
@@ -1,23 +1,31 @@
 use crate::ai::Ai;
 use crate::game::*;
+use crate::info::*;
+use crate::limits::Limits;
 use rand::prelude::SliceRandom;
 use rand::*;
 use rand_xoshiro::Xoroshiro128Plus;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
 pub struct MonteCarloTotal<G: Game> {
 	pub g: G,
 	rng: Xoroshiro128Plus,
+	// rollout buffer
+	moves: Vec<G::M>,
+	stop: Arc<AtomicBool>,
+	reporter: Reporter<G::M>,
 }
 
 impl<G: Game> MonteCarloTotal<G> {
 	fn explore_branch(&mut self, m0: &G::M, turn: bool) -> u32 {
 		self.g.mov(&m0);
 		while self.g.state() == State::Going {
-			let moves = self.g.get_moves();
-			let m = moves.choose(&mut self.rng).unwrap();
-			self.g.mov(&m);
+			self.g.fill_moves(&mut self.moves);
+			let m = self.moves.choose(&mut self.rng).unwrap();
+			self.g.mov(m);
 		}
 		let mut ans = match self.g.state() {
 			State::Win => 1,
@@ -36,6 +44,9 @@ impl<G: Game> Ai<G> for MonteCarloTotal<G> {
 		Self {
 			g: G::new(t),
 			rng: Xoroshiro128Plus::from_rng(rand::thread_rng()).unwrap(),
+			moves: Vec::new(),
+			stop: Arc::default(),
+			reporter: Reporter::default(),
 		}
 	}
 	fn state(&self) -> State {
@@ -52,16 +63,20 @@ impl<G: Game> Ai<G> for MonteCarloTotal<G> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, mut tl: Duration) -> G::M {
+	// a depth limit allows a single round of playouts
+	fn think(&mut self, limits: Limits) -> G::M {
 		let start_time = Instant::now();
-		tl -= Duration::from_millis(20);
 		let moves = self.g.get_moves();
 		let turn = self.g.turn();
 		let mut v = vec![0u32; moves.len()];
-		let mut i = 0;
+		let mut i = 0u64;
 		let g0 = self.g.clone();
 		loop {
-			if start_time.elapsed() > tl {
+			let playouts = i * moves.len() as u64;
+			if (i > 0 && limits.depth.is_some())
+				|| limits.expired(start_time, playouts)
+				|| self.stop.load(Ordering::Relaxed)
+			{
 				break;
 			}
 			i += 1;
@@ -72,15 +87,21 @@ impl<G: Game> Ai<G> for MonteCarloTotal<G> {
 		}
 		let best_ind = v.iter().enumerate().max_by_key(|x| x.1).unwrap().0;
 		let ans = moves[best_ind];
-		eprintln!(
-			"monte_carlo_total chose move in {} milliseconds with {} iterations | confidence: {}",
-			start_time.elapsed().as_millis(),
-			i,
-			v[best_ind] as f32 / i as f32,
-		);
+		let playouts = i * moves.len() as u64;
+		let info = SearchInfo::new(1, None, playouts, start_time.elapsed(), vec![ans]);
+		self.reporter.report(&info);
 		ans
 	}
 	fn mov(&mut self, m: &G::M) {
 		self.g.mov(m);
 	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_seed(&mut self, seed: u64) {
+		self.rng = Xoroshiro128Plus::seed_from_u64(seed);
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
@@ -1,13 +1,19 @@
 use crate::ai::Ai;
 use crate::game::*;
-use rand::prelude::SliceRandom;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::rollout::*;
 use rand::*;
 use rand_xoshiro::Xoroshiro128Plus;
-use std::time::Duration;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Instant;
 
 struct Tree<G: Game> {
-	wins: u32,
+	// for white, playouts cut short count the chance of a win
+	wins: f64,
 	vis: u32,
 	movs: Vec<G::M>,
 	children: Vec<Tree<G>>,
@@ -15,12 +21,28 @@ struct Tree<G: Game> {
 impl<G: Game> Tree<G> {
 	fn new() -> Self {
 		Self {
-			wins: 0,
+			wins: 0.0,
 			vis: 0,
 			movs: vec![],
 			children: vec![],
 		}
 	}
+	// the most visited line
+	fn principal_variation(&self) -> Vec<G::M> {
+		let mut pv = Vec::new();
+		let mut t = self;
+		while let Some((m, c)) = t
+			.movs
+			.iter()
+			.zip(t.children.iter())
+			.filter(|(_, c)| c.vis != 0)
+			.max_by_key(|(_, c)| c.vis)
+		{
+			pv.push(*m);
+			t = c;
+		}
+		pv
+	}
 }
 impl<G: Game> Default for Tree<G> {
 	fn default() -> Self {
@@ -28,29 +50,23 @@ impl<G: Game> Default for Tree<G> {
 	}
 }
 
-pub struct MonteCarloTreeSearch<G: Game> {
+// R finishes the playouts from the leaves
+pub struct MonteCarloTreeSearch<G: Game, R: Rollout<G> = RandomRollout> {
 	pub g: G,
 	rng: Xoroshiro128Plus,
+	// rollout buffer
+	moves: Vec<G::M>,
 	tree: Tree<G>,
+	stop: Arc<AtomicBool>,
+	reporter: Reporter<G::M>,
+	_ph: PhantomData<R>,
 }
 
-impl<G: Game> MonteCarloTreeSearch<G> {
-	fn result_u32(&mut self, s: State) -> u32 {
-		match s {
-			State::Win => 1u32,
-			State::Lose => 0u32,
-			_ => self.rng.next_u32() & 1,
-		}
-	}
-	fn explore_branch(&mut self) -> u32 {
-		while self.g.state() == State::Going {
-			let moves = self.g.get_moves();
-			let m = moves.choose(&mut self.rng).unwrap();
-			self.g.mov(&m);
-		}
-		self.result_u32(self.g.state())
+impl<G: Game, R: Rollout<G>> MonteCarloTreeSearch<G, R> {
+	fn explore_branch(&mut self) -> f64 {
+		R::play(&mut self.g, &mut self.moves, &mut self.rng)
 	}
-	fn step(&mut self, t: &mut Tree<G>) -> u32 {
+	fn step(&mut self, t: &mut Tree<G>) -> f64 {
 		let turn = self.g.turn();
 		if self.g.state() != State::Going || t.vis == 0 {
 			t.vis += 1;
@@ -65,11 +81,12 @@ impl<G: Game> MonteCarloTreeSearch<G> {
 			t.children.push(Tree::<G>::new());
 			t.children.len() - 1
 		} else {
-			let mut best_val = 0.0f32;
+			let mut best_val = 0.0;
 			let mut ans = 0;
 			for (i, x) in t.children.iter().enumerate() {
-				let val = (if turn { x.wins } else { x.vis - x.wins }) as f32 / x.vis as f32
-					+ 1.5 * ((t.vis as f32).ln() / (x.vis as f32)).sqrt();
+				let vis = x.vis as f64;
+				let val = (if turn { x.wins } else { vis - x.wins }) / vis
+					+ 1.5 * ((t.vis as f64).ln() / vis).sqrt();
 				if val > best_val {
 					best_val = val;
 					ans = i;
@@ -83,14 +100,52 @@ impl<G: Game> MonteCarloTreeSearch<G> {
 		t.vis += 1;
 		x
 	}
+	// steps from the current position until a limit is hit or stop is set,
+	// returns how many
+	pub(crate) fn search(&mut self, limits: &Limits, stop: &AtomicBool) -> usize {
+		let start_time = Instant::now();
+		let mut i = 0;
+		let mut t = std::mem::take(&mut self.tree);
+		let g0 = self.g.clone();
+		loop {
+			for _ in 0..32 {
+				self.step(&mut t);
+				self.g = g0.clone();
+			}
+			i += 32;
+			let deep_enough = limits
+				.depth
+				.is_some_and(|d| t.principal_variation().len() >= d as usize);
+			if deep_enough || limits.expired(start_time, i as u64) || stop.load(Ordering::Relaxed) {
+				break;
+			}
+		}
+		self.tree = t;
+		i
+	}
+	// how many times each root move was tried
+	pub(crate) fn root_visits(&self) -> impl Iterator<Item = (G::M, u32)> + '_ {
+		self.tree
+			.movs
+			.iter()
+			.zip(self.tree.children.iter())
+			.map(|(m, t)| (*m, t.vis))
+	}
+	pub(crate) fn principal_variation(&self) -> Vec<G::M> {
+		self.tree.principal_variation()
+	}
 }
 
-impl<G: Game> Ai<G> for MonteCarloTreeSearch<G> {
+impl<G: Game, R: Rollout<G>> Ai<G> for MonteCarloTreeSearch<G, R> {
 	fn new(t: bool) -> Self {
 		Self {
 			g: G::new(t),
 			rng: Xoroshiro128Plus::from_rng(rand::thread_rng()).unwrap(),
+			moves: Vec::new(),
 			tree: Tree::<G>::new(),
+			stop: Arc::default(),
+			reporter: Reporter::default(),
+			_ph: PhantomData,
 		}
 	}
 	fn state(&self) -> State {
@@ -107,38 +162,22 @@ impl<G: Game> Ai<G> for MonteCarloTreeSearch<G> {
 	fn turn(&self) -> bool {
 		self.g.turn()
 	}
-	fn get_mov(&mut self, mut tl: Duration) -> G::M {
+	fn think(&mut self, limits: Limits) -> G::M {
 		let start_time = Instant::now();
-		tl -= Duration::from_millis(20);
 		let moves = self.g.get_moves();
-		let mut i = 0;
-		let mut t = std::mem::take(&mut self.tree);
-		let g0 = self.g.clone();
-		loop {
-			for _ in 0..32 {
-				self.step(&mut t);
-				self.g = g0.clone();
-			}
-			i += 32;
-			if start_time.elapsed() > tl {
-				break;
-			}
-		}
-		self.tree = std::mem::take(&mut t);
+		let stop = self.stop.clone();
+		let i = self.search(&limits, &stop);
 		let mut best_mov = moves[0];
 		let mut best_val = 0;
-		for (i, t) in self.tree.children.iter().enumerate() {
-			let val = t.vis;
+		for (m, val) in self.root_visits() {
 			if val > best_val {
 				best_val = val;
-				best_mov = self.tree.movs[i];
+				best_mov = m;
 			}
 		}
-		eprintln!(
-			"monte_carlo_tree_search chose move in {} milliseconds with {} iterations",
-			start_time.elapsed().as_millis(),
-			i,
-		);
+		let pv = self.principal_variation();
+		let info = SearchInfo::new(pv.len() as u32, None, i as u64, start_time.elapsed(), pv);
+		self.reporter.report(&info);
 		best_mov
 	}
 	fn mov(&mut self, m: &G::M) {
@@ -155,4 +194,19 @@ impl<G: Game> Ai<G> for MonteCarloTreeSearch<G> {
 			self.tree = std::mem::take(&mut t.children[movi])
 		}
 	}
+	// grows the tree from the opponent's position, mov keeps the subtree
+	fn ponder(&mut self, stop: Arc<AtomicBool>) {
+		if self.g.state() == State::Going {
+			self.search(&Limits::infinite(), &stop);
+		}
+	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_seed(&mut self, seed: u64) {
+		self.rng = Xoroshiro128Plus::seed_from_u64(seed);
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
 }
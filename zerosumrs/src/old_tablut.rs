@@ -1,5 +1,5 @@
 use crate::game::*;
-//use rustc_hash::*;
+use crate::tablut::MoveError;
 
 #[derive(Hash, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Tile {
@@ -151,7 +151,7 @@ pub struct Tablut {
 	board: [u8; 21],
 	pub turn: u32, //%2=0 defender, %2=1 attacker
 	state: State,
-	//vis: FxHashSet<<Tablut as Game>::S>,
+	history: Vec<<Tablut as Game>::S>,
 }
 pub fn mapc(x: u8, y: u8) -> u8 {
 	y * 9 + x
@@ -197,26 +197,28 @@ impl Game for Tablut {
 	type M = (u8, u8); // compressed coords from and to (4bits x, 4bits y)
 	type S = ([u8; 21], bool);
 	type R = (Self::M, u8); // first: coords, second: ruld tiles
+	type E = MoveError;
 	fn new(t: bool) -> Self {
 		let mut ans = Tablut {
 			board: <[u8; 21]>::default(),
 			turn: if t { 0 } else { 1 },
 			state: State::Going,
-			//vis: FxHashSet::default(),
+			history: vec![],
 		};
 		for y in 0..9 {
 			for x in 0..9 {
 				ans.set(mapc(x, y), STARTING_POSITION[y as usize][x as usize]);
 			}
 		}
-		//ans.vis.insert(ans.get_static_state());
+		ans.history.push(ans.get_static_state());
 		ans
 	}
 	fn turn(&self) -> bool {
 		(self.turn & 1) == 0
 	}
-	fn get_moves(&self) -> Vec<Self::M> {
-		let mut ans = Vec::<Self::M>::with_capacity(96);
+	fn fill_moves(&self, ans: &mut Vec<Self::M>) {
+		ans.clear();
+		ans.reserve(96);
 
 		// right
 		for y in 0..9 {
@@ -305,10 +307,9 @@ impl Game for Tablut {
 		if ans.is_empty() {
 			ans.push((40, 40));
 		}
-		ans
 	}
-	fn get_moves_sorted(&self) -> Vec<Self::M> {
-		let mut ans = self.get_moves();
+	fn fill_moves_sorted(&self, ans: &mut Vec<Self::M>) {
+		self.fill_moves(ans);
 		ans.sort_unstable_by_key(|m| {
 			const ORDI: [[u8; 9]; 2] = [
 				// 0,1,2,3,4,5,6,7,8   // old order
@@ -335,7 +336,13 @@ impl Game for Tablut {
 				}
 			}
 		});
-		ans
+	}
+	const SQUARES: usize = 81;
+	fn squares(m: &Self::M) -> (usize, usize) {
+		(m.0 as usize, m.1 as usize)
+	}
+	fn from_squares(from: usize, to: usize) -> Self::M {
+		(from as u8, to as u8)
 	}
 	fn get_static_state(&self) -> Self::S {
 		(self.board, self.turn())
@@ -343,6 +350,51 @@ impl Game for Tablut {
 	fn state(&self) -> State {
 		self.state
 	}
+	fn check_mov(&self, m: &Self::M) -> Result<(), Self::E> {
+		if self.state != State::Going {
+			return Err(MoveError::GameOver);
+		}
+		if m.0 == m.1 {
+			return match self.get_moves()[0] {
+				(40, 40) => Ok(()),
+				_ => Err(MoveError::Pass),
+			};
+		}
+		for p in [m.0, m.1].iter() {
+			if *p >= 81 {
+				return Err(MoveError::OffBoard(*p));
+			}
+		}
+		let coords = |p: u8| (p % 9, p / 9);
+		match self.get(m.0) {
+			Tile::E => return Err(MoveError::Empty(coords(m.0))),
+			t if self.turn() == (t == Tile::A) => {
+				return Err(MoveError::WrongSide(coords(m.0)));
+			}
+			_ => {}
+		}
+		let ((fx, fy), (tx, ty)) = (coords(m.0), coords(m.1));
+		let step = if fy == ty {
+			1
+		} else if fx == tx {
+			9
+		} else {
+			return Err(MoveError::NotStraight);
+		};
+		let mut p = m.0;
+		while p != m.1 {
+			p = if m.1 > m.0 { p + step } else { p - step };
+			if self.get(p) != Tile::E {
+				return Err(MoveError::Blocked(coords(p)));
+			}
+			// attackers can move inside their own citadel
+			let dist = p.abs_diff(m.0) / step;
+			if is_block_um(p) && !(is_block_um(m.0) && dist <= 2) {
+				return Err(MoveError::Citadel(coords(p)));
+			}
+		}
+		Ok(())
+	}
 	fn mov(&mut self, m: &Self::M) {
 		if m.0 == m.1 {
 			self.state = match self.turn() {
@@ -383,9 +435,11 @@ impl Game for Tablut {
 			self.state = State::Win;
 		}
 		self.turn += 1;
-		/*if !self.vis.insert(self.get_static_state()) {
+		let pos = self.get_static_state();
+		if self.state == State::Going && self.history.contains(&pos) {
 			self.state = State::Draw;
-		}*/
+		}
+		self.history.push(pos);
 	}
 	fn mov_with_rollback(&mut self, m: &Self::M) -> Self::R {
 		let mut rb = (*m, 0u8);
@@ -405,9 +459,9 @@ impl Game for Tablut {
 		rb
 	}
 	fn rollback(&mut self, rbf: Self::R) {
-		/*if self.state != State::Draw {
-			self.vis.remove(&self.get_static_state());
-		}*/
+		if rbf.0 .0 != rbf.0 .1 {
+			self.history.pop();
+		}
 		self.turn -= 1;
 		self.state = State::Going;
 		let (m, rb) = rbf;
@@ -0,0 +1,115 @@
+use crate::game::*;
+use std::cmp::Reverse;
+
+// history scores are halved when one gets past this
+const HISTORY_MAX: i64 = 1 << 24;
+// order of the special moves, above any history score
+const HASH: i64 = 1 << 40;
+const KILLER: [i64; 2] = [1 << 39, 1 << 38];
+const COUNTER: i64 = 1 << 37;
+
+// move ordering for the alpha-beta searchers: the hash move, the two killers of
+// the ply, the counter to the previous move, then the rest by history score.
+// moves that tie keep the order they were generated in
+pub struct MoveOrdering<G: Game> {
+	killers: Vec<[G::M; 2]>,
+	// butterfly tables, indexed by side, from and to
+	history: Vec<i64>,
+	counter: Vec<G::M>,
+	scratch: Vec<(i64, usize, G::M)>,
+}
+
+impl<G: Game> Default for MoveOrdering<G> {
+	fn default() -> Self {
+		let n = 2 * G::SQUARES * G::SQUARES;
+		Self {
+			killers: Vec::new(),
+			history: vec![0; n],
+			counter: vec![G::M::default(); n],
+			scratch: Vec::new(),
+		}
+	}
+}
+
+impl<G: Game> MoveOrdering<G> {
+	fn index(side: bool, m: &G::M) -> Option<usize> {
+		if G::SQUARES == 0 {
+			return None;
+		}
+		let (from, to) = G::squares(m);
+		Some((side as usize * G::SQUARES + from) * G::SQUARES + to)
+	}
+	pub fn clear(&mut self) {
+		*self = Self::default();
+	}
+	// call before each search, killers are forgotten and history fades
+	pub fn new_search(&mut self) {
+		self.killers.clear();
+		self.history.iter_mut().for_each(|h| *h /= 2);
+	}
+	pub fn killers(&self, ply: usize) -> [G::M; 2] {
+		self.killers
+			.get(ply)
+			.copied()
+			.unwrap_or([G::M::default(); 2])
+	}
+	// the move that last refuted prev, played by the other side
+	pub fn counter(&self, side: bool, prev: &G::M) -> Option<G::M> {
+		Self::index(!side, prev).map(|i| self.counter[i])
+	}
+	pub fn history(&self, side: bool, m: &G::M) -> i64 {
+		Self::index(side, m).map_or(0, |i| self.history[i])
+	}
+	// sort the moves of side at ply, played after prev
+	pub fn order(
+		&mut self,
+		moves: &mut [G::M],
+		hash: Option<G::M>,
+		ply: usize,
+		side: bool,
+		prev: &G::M,
+	) {
+		let killers = self.killers(ply);
+		let counter = self.counter(side, prev);
+		self.scratch.clear();
+		for (i, m) in moves.iter().enumerate() {
+			let score = if Some(*m) == hash {
+				HASH
+			} else if *m == killers[0] {
+				KILLER[0]
+			} else if *m == killers[1] {
+				KILLER[1]
+			} else if Some(*m) == counter {
+				COUNTER
+			} else {
+				self.history(side, m)
+			};
+			self.scratch.push((score, i, *m));
+		}
+		self.scratch
+			.sort_unstable_by_key(|&(score, i, _)| (Reverse(score), i));
+		for (m, &(_, _, sm)) in moves.iter_mut().zip(self.scratch.iter()) {
+			*m = sm;
+		}
+	}
+	// m of side at ply caused a cutoff with depth left to search
+	pub fn cutoff(&mut self, m: &G::M, ply: usize, side: bool, prev: &G::M, depth: u32) {
+		if self.killers.len() <= ply {
+			self.killers.resize(ply + 1, [G::M::default(); 2]);
+		}
+		let k = &mut self.killers[ply];
+		if k[0] != *m {
+			k[1] = k[0];
+			k[0] = *m;
+		}
+		if let Some(i) = Self::index(!side, prev) {
+			self.counter[i] = *m;
+		}
+		if let Some(i) = Self::index(side, m) {
+			self.history[i] += (depth * depth) as i64;
+			if self.history[i] > HISTORY_MAX {
+				self.history.iter_mut().for_each(|h| *h /= 2);
+			}
+		}
+	}
+}
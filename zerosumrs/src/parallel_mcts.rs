@@ -0,0 +1,141 @@
+use crate::ai::Ai;
+use crate::game::*;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::monte_carlo_tree_search::MonteCarloTreeSearch;
+use crate::rollout::*;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+// root parallel mcts: every thread grows its own tree from the same position
+// with its own random numbers, then the visits of the root moves are summed
+pub struct ParallelMcts<G: Game, R: Rollout<G> = RandomRollout> {
+	pub g: G,
+	pub threads: usize,
+	trees: Vec<MonteCarloTreeSearch<G, R>>,
+	stop: Arc<AtomicBool>,
+	// tree i plays from seed + i
+	seed: Option<u64>,
+	reporter: Reporter<G::M>,
+}
+
+impl<G: Game, R: Rollout<G>> ParallelMcts<G, R> {
+	pub fn with_threads(t: bool, threads: usize) -> Self {
+		Self {
+			g: G::new(t),
+			threads: threads.max(1),
+			trees: Vec::new(),
+			stop: Arc::default(),
+			seed: None,
+			reporter: Reporter::default(),
+		}
+	}
+}
+
+impl<G, R> ParallelMcts<G, R>
+where
+	G: Game + Send,
+	G::M: Send,
+	R: Rollout<G> + Send,
+{
+	// every tree searches in its own thread, returns the total iterations.
+	// the playouts are split between the trees
+	fn run(&mut self, limits: Limits, stop: &AtomicBool) -> usize {
+		let limits = Limits {
+			nodes: limits.nodes.map(|n| n / self.threads as u64),
+			..limits
+		};
+		self.trees.truncate(self.threads);
+		while self.trees.len() < self.threads {
+			let mut t = MonteCarloTreeSearch::new(true);
+			t.g = self.g.clone();
+			if let Some(seed) = self.seed {
+				t.set_seed(seed.wrapping_add(self.trees.len() as u64));
+			}
+			self.trees.push(t);
+		}
+		std::thread::scope(|s| {
+			let handles: Vec<_> = self
+				.trees
+				.iter_mut()
+				.map(|t| s.spawn(move || t.search(&limits, stop)))
+				.collect();
+			handles.into_iter().map(|h| h.join().unwrap()).sum()
+		})
+	}
+}
+
+impl<G, R> Ai<G> for ParallelMcts<G, R>
+where
+	G: Game + Send,
+	G::M: Send,
+	R: Rollout<G> + Send,
+{
+	// one thread per core
+	fn new(t: bool) -> Self {
+		let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+		Self::with_threads(t, threads)
+	}
+	fn state(&self) -> State {
+		self.g.state()
+	}
+	fn print2game(&self) {
+		eprintln!("{}", self.g)
+	}
+	fn get_game(&self) -> &G {
+		&self.g
+	}
+	fn turn(&self) -> bool {
+		self.g.turn()
+	}
+	fn think(&mut self, limits: Limits) -> G::M {
+		let start_time = Instant::now();
+		let stop = self.stop.clone();
+		let i = self.run(limits, &stop);
+		let mut visits: Vec<(G::M, u32)> = Vec::new();
+		for (m, v) in self.trees.iter().flat_map(|t| t.root_visits()) {
+			match visits.iter_mut().find(|(vm, _)| *vm == m) {
+				Some((_, vv)) => *vv += v,
+				None => visits.push((m, v)),
+			}
+		}
+		let best_mov = match visits.iter().max_by_key(|(_, v)| *v) {
+			Some((m, _)) => *m,
+			None => self.g.get_moves()[0],
+		};
+		// the rest of the line from the first tree that agrees on the move
+		let pv = self
+			.trees
+			.iter()
+			.map(|t| t.principal_variation())
+			.find(|pv| pv.first() == Some(&best_mov))
+			.unwrap_or_else(|| vec![best_mov]);
+		let info = SearchInfo::new(pv.len() as u32, None, i as u64, start_time.elapsed(), pv);
+		self.reporter.report(&info);
+		best_mov
+	}
+	fn mov(&mut self, m: &G::M) {
+		self.g.mov(m);
+		for t in self.trees.iter_mut() {
+			t.mov(m);
+		}
+	}
+	fn ponder(&mut self, stop: Arc<AtomicBool>) {
+		if self.g.state() == State::Going {
+			self.run(Limits::infinite(), &stop);
+		}
+	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_seed(&mut self, seed: u64) {
+		self.seed = Some(seed);
+		for (i, t) in self.trees.iter_mut().enumerate() {
+			t.set_seed(seed.wrapping_add(i as u64));
+		}
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
+}
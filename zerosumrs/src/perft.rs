@@ -0,0 +1,46 @@
+use crate::game::*;
+
+fn perft_with<G: Game>(g: &mut G, depth: u32, buffers: &mut MoveBuffers<G::M>) -> u64 {
+	if depth == 0 {
+		return 1;
+	}
+	if g.state() != State::Going {
+		return 0;
+	}
+	let mut moves = buffers.take(depth);
+	g.fill_moves(&mut moves);
+	let mut ans = 0;
+	if depth == 1 {
+		ans = moves.len() as u64;
+	} else {
+		for m in moves.iter() {
+			let rb = g.mov_with_rollback(m);
+			ans += perft_with(g, depth - 1, buffers);
+			g.rollback(rb);
+		}
+	}
+	buffers.put(depth, moves);
+	ans
+}
+
+// number of positions reached after exactly depth plies,
+// games ending before that don't contribute
+pub fn perft<G: Game>(g: &mut G, depth: u32) -> u64 {
+	perft_with(g, depth, &mut MoveBuffers::default())
+}
+
+// perft split by root move, to find which move a mismatch comes from
+pub fn divide<G: Game>(g: &mut G, depth: u32) -> Vec<(G::M, u64)> {
+	if depth == 0 || g.state() != State::Going {
+		return vec![];
+	}
+	let moves = g.get_moves();
+	let mut buffers = MoveBuffers::default();
+	let mut ans = Vec::with_capacity(moves.len());
+	for m in moves.iter() {
+		let rb = g.mov_with_rollback(m);
+		ans.push((*m, perft_with(g, depth - 1, &mut buffers)));
+		g.rollback(rb);
+	}
+	ans
+}
@@ -0,0 +1,309 @@
+use crate::ai::Ai;
+use crate::game::*;
+use crate::heuristic::Heuristic;
+use crate::info::*;
+use crate::limits::Limits;
+use crate::ordering::MoveOrdering;
+use crate::quiescence::Quiescence;
+use crate::tt::*;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+// first aspiration window around the previous score, widened on each fail
+const WINDOW: i64 = 16;
+// moves searched at full depth before late move reductions start
+const LMR_MOVES: usize = 4;
+// no null move with fewer legal moves than this, zugzwang gets likely
+const NULL_MIN_MOVES: usize = 8;
+// iterative deepening gives up here, only reached in decided positions
+const MAX_DEPTH: u32 = 64;
+
+// principal variation search: the first move gets the full window, the others
+// a null window that only proves them worse, re-searched if they aren't.
+// Q is the node limit of the quiescence search at the leaves, 0 turns it off.
+// LMR searches late quiet moves shallower, NULL tries passing the turn first
+// and prunes the node if that is still good enough
+pub struct Pvs<
+	G: Game,
+	H: Heuristic<G>,
+	const Q: u32 = 0,
+	const LMR: bool = false,
+	const NULL: bool = false,
+> {
+	pub g: G,
+	nnw: u8,
+	limits: Limits,
+	st: Instant,
+	ended_early: bool,
+	stop: Arc<AtomicBool>,
+	nodes: u64,
+	reporter: Reporter<G::M>,
+	root_depth: u32,
+	root_best: G::M,
+	table: TranspositionTable<G::M>,
+	moves: MoveBuffers<G::M>,
+	ordering: MoveOrdering<G>,
+	q: Quiescence<G>,
+	_ph: PhantomData<H>,
+}
+
+impl<G: Game, H: Heuristic<G>, const Q: u32, const LMR: bool, const NULL: bool>
+	Pvs<G, H, Q, LMR, NULL>
+{
+	// prev is the move that led here, the default move after a null move
+	fn pvs(&mut self, mut a: i64, mut b: i64, depth: u32, prev: G::M) -> i64 {
+		if self.g.state() != State::Going || depth == 0 {
+			return self.q.eval::<H>(&mut self.g, a, b, Q);
+		}
+		self.nnw = self.nnw.wrapping_add(1);
+		self.nodes += 1;
+		if self.ended_early
+			|| (self.nnw == 0
+				&& (self.limits.expired(self.st, self.nodes + self.q.visited)
+					|| self.stop.load(Ordering::Relaxed)))
+		{
+			self.ended_early = true;
+			return if self.g.turn() { a } else { b };
+		}
+		let (a0, b0) = (a, b);
+		let key = self.g.get_hash();
+		let root = depth == self.root_depth;
+		let mut hash_mov = if root { Some(self.root_best) } else { None };
+		if let Some(e) = self.table.probe(key) {
+			if let Some(v) = e.cutoff(depth, a, b).filter(|_| !root) {
+				return v;
+			}
+			hash_mov = hash_mov.or(Some(e.mov));
+		}
+		let mut moves = self.moves.take(depth);
+		self.g.fill_moves_sorted(&mut moves);
+		let white = self.g.turn();
+		if NULL
+			&& !root && depth >= 3
+			&& b.saturating_sub(a) == 1
+			&& prev != G::M::default()
+			&& moves.len() >= NULL_MIN_MOVES
+		{
+			if let Some(v) = self.null_move(a, b, depth) {
+				self.moves.put(depth, moves);
+				return v;
+			}
+		}
+		let ply = (self.root_depth - depth) as usize;
+		self.ordering.order(&mut moves, hash_mov, ply, white, &prev);
+		let mut res = if white { i64::MIN } else { i64::MAX };
+		let mut best = moves[0];
+		for (i, m) in moves.iter().enumerate() {
+			let r = if LMR && i >= LMR_MOVES && depth >= 3 && !self.g.is_forcing(m) {
+				if i >= 3 * LMR_MOVES && depth >= 5 {
+					2
+				} else {
+					1
+				}
+			} else {
+				0
+			};
+			let rb = self.g.mov_with_rollback(m);
+			let h = if i == 0 {
+				self.pvs(a, b, depth - 1, *m)
+			} else {
+				let (na, nb) = if white {
+					(a, a.saturating_add(1))
+				} else {
+					(b.saturating_sub(1), b)
+				};
+				let mut h = self.pvs(na, nb, depth - 1 - r, *m);
+				// a reduced move that seems better gets its full depth back
+				if r > 0 && (if white { h > a } else { h < b }) {
+					h = self.pvs(na, nb, depth - 1, *m);
+				}
+				if h > a && h < b {
+					h = if white {
+						self.pvs(h, b, depth - 1, *m)
+					} else {
+						self.pvs(a, h, depth - 1, *m)
+					};
+				}
+				h
+			};
+			self.g.rollback(rb);
+			if self.ended_early {
+				break;
+			}
+			if white {
+				if h > res {
+					res = h;
+					best = *m;
+				}
+				a = a.max(h);
+			} else {
+				if h < res {
+					res = h;
+					best = *m;
+				}
+				b = b.min(h);
+			}
+			if a >= b {
+				self.ordering.cutoff(m, ply, white, &prev, depth);
+				break;
+			}
+		}
+		self.moves.put(depth, moves);
+		if !self.ended_early {
+			self.table
+				.store(key, res, depth, Bound::of(res, a0, b0), best);
+			if root {
+				self.root_best = best;
+			}
+		}
+		res
+	}
+	// let the other side move twice, if we still beat the null window the
+	// node is cut. only tried where the static eval is already past it
+	fn null_move(&mut self, a: i64, b: i64, depth: u32) -> Option<i64> {
+		let white = self.g.turn();
+		let eval = H::eval(&self.g);
+		if (white && eval < b) || (!white && eval > a) {
+			return None;
+		}
+		let rb = self.g.null_mov()?;
+		let d = depth.saturating_sub(3 + depth / 6);
+		let h = if white {
+			self.pvs(b.saturating_sub(1), b, d, G::M::default())
+		} else {
+			self.pvs(a, a.saturating_add(1), d, G::M::default())
+		};
+		self.g.rollback(rb);
+		if self.ended_early {
+			None
+		} else if white && h >= b {
+			Some(b)
+		} else if !white && h <= a {
+			Some(a)
+		} else {
+			None
+		}
+	}
+	// search with a narrow window around the last score, widening it on fails
+	fn aspiration(&mut self, last: i64, depth: u32) -> i64 {
+		let mut delta = WINDOW;
+		let mut a = last.saturating_sub(delta);
+		let mut b = last.saturating_add(delta);
+		loop {
+			let h = self.pvs(a, b, depth, G::M::default());
+			if self.ended_early || (h > a && h < b) {
+				return h;
+			}
+			delta = delta.saturating_mul(4);
+			if h <= a {
+				a = h.saturating_sub(delta);
+			} else {
+				b = h.saturating_add(delta);
+			}
+		}
+	}
+	// iterative deepening until a limit is hit, returns the last completed
+	// depth and its score, the best move is left in root_best
+	fn deepen(&mut self) -> (u32, i64) {
+		self.st = Instant::now();
+		self.ended_early = false;
+		self.table.new_search();
+		self.ordering.new_search();
+		self.nodes = 0;
+		self.q.reset_stats();
+		self.root_best = self.g.get_moves_sorted()[0];
+		let mut ans = self.root_best;
+		let mut val = 0;
+		let mut depth = 0;
+		while !self.ended_early && depth < MAX_DEPTH && self.limits.allows_depth(depth + 1) {
+			depth += 1;
+			self.root_depth = depth;
+			let h = if depth == 1 {
+				self.pvs(i64::MIN, i64::MAX, depth, G::M::default())
+			} else {
+				self.aspiration(val, depth)
+			};
+			if !self.ended_early {
+				val = h;
+				ans = self.root_best;
+				self.report(depth, val);
+			} else {
+				depth -= 1;
+			}
+		}
+		self.root_best = ans;
+		(depth, val)
+	}
+	fn report(&mut self, depth: u32, val: i64) {
+		let table = &self.table;
+		let pv = principal_variation(&self.g, depth, |k| table.probe(k).map(|e| e.mov));
+		let nodes = self.nodes + self.q.visited;
+		let mut info = SearchInfo::new(depth, Some(val), nodes, self.st.elapsed(), pv);
+		info.seldepth = depth + self.q.max_ply;
+		self.reporter.report(&info);
+	}
+}
+
+impl<G: Game, H: Heuristic<G>, const Q: u32, const LMR: bool, const NULL: bool> Ai<G>
+	for Pvs<G, H, Q, LMR, NULL>
+{
+	fn new(t: bool) -> Self {
+		Self {
+			g: G::new(t),
+			nnw: 0,
+			limits: Limits::default(),
+			st: Instant::now(),
+			ended_early: false,
+			stop: Arc::default(),
+			nodes: 0,
+			reporter: Reporter::default(),
+			root_depth: 0,
+			root_best: G::M::default(),
+			table: TranspositionTable::new(18),
+			moves: MoveBuffers::default(),
+			ordering: MoveOrdering::default(),
+			q: Quiescence::default(),
+			_ph: PhantomData,
+		}
+	}
+	fn state(&self) -> State {
+		self.g.state()
+	}
+	fn get_game(&self) -> &G {
+		&self.g
+	}
+	fn print2game(&self) {
+		eprintln!("{}", self.g)
+	}
+	fn turn(&self) -> bool {
+		self.g.turn()
+	}
+	fn think(&mut self, limits: Limits) -> G::M {
+		self.limits = limits;
+		self.deepen();
+		self.root_best
+	}
+	fn mov(&mut self, m: &G::M) {
+		self.g.mov(m);
+	}
+	// the table keeps what is found for the positions after the reply
+	fn ponder(&mut self, stop: Arc<AtomicBool>) {
+		if self.g.state() != State::Going {
+			return;
+		}
+		// the search answers to the ponder flag meanwhile
+		let handle = std::mem::replace(&mut self.stop, stop);
+		self.limits = Limits::infinite();
+		self.deepen();
+		self.stop = handle;
+	}
+	fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
+}
@@ -0,0 +1,92 @@
+use crate::game::*;
+use crate::heuristic::Heuristic;
+
+// extends the leaves of a search with the forcing moves of the game until the
+// position is quiet, so H::eval isn't called in the middle of an exchange
+pub struct Quiescence<G: Game> {
+	moves: MoveBuffers<G::M>,
+	budget: u32,
+	// positions evaluated and deepest ply reached since reset_stats
+	pub visited: u64,
+	pub max_ply: u32,
+}
+
+impl<G: Game> Default for Quiescence<G> {
+	fn default() -> Self {
+		Self {
+			moves: MoveBuffers::default(),
+			budget: 0,
+			visited: 0,
+			max_ply: 0,
+		}
+	}
+}
+
+impl<G: Game> Quiescence<G> {
+	// value of g searching at most limit positions, just H::eval if limit is 0
+	pub fn eval<H: Heuristic<G>>(&mut self, g: &mut G, a: i64, b: i64, limit: u32) -> i64 {
+		self.budget = limit;
+		self.search::<H>(g, a, b, 0)
+	}
+	pub fn reset_stats(&mut self) {
+		self.visited = 0;
+		self.max_ply = 0;
+	}
+	fn search<H: Heuristic<G>>(&mut self, g: &mut G, mut a: i64, mut b: i64, ply: u32) -> i64 {
+		self.visited += 1;
+		self.max_ply = self.max_ply.max(ply);
+		let stand = H::eval(g);
+		if g.state() != State::Going || self.budget == 0 {
+			return stand;
+		}
+		// the side to move can decline the forcing moves, unless it is
+		// threatened, then every reply is searched and none is declined
+		let threatened = g.is_threatened();
+		if !threatened {
+			if g.turn() {
+				if stand >= b {
+					return stand;
+				}
+				a = a.max(stand);
+			} else {
+				if stand <= a {
+					return stand;
+				}
+				b = b.min(stand);
+			}
+		}
+		let mut res = stand;
+		let mut moves = self.moves.take(ply);
+		if threatened {
+			g.fill_moves(&mut moves);
+			moves.sort_by_key(|m| !g.is_forcing(m));
+		} else {
+			g.fill_forcing_moves(&mut moves);
+		}
+		for (i, m) in moves.iter().enumerate() {
+			if self.budget == 0 {
+				break;
+			}
+			self.budget -= 1;
+			let rb = g.mov_with_rollback(m);
+			let h = self.search::<H>(g, a, b, ply + 1);
+			g.rollback(rb);
+			// stand is only a guess until the first reply is searched
+			if threatened && i == 0 {
+				res = h;
+			}
+			if g.turn() {
+				res = res.max(h);
+				a = a.max(h);
+			} else {
+				res = res.min(h);
+				b = b.min(h);
+			}
+			if a >= b {
+				break;
+			}
+		}
+		self.moves.put(ply, moves);
+		res
+	}
+}
@@ -0,0 +1,57 @@
+use crate::ai::Ai;
+use crate::game::*;
+use crate::info::*;
+use crate::limits::Limits;
+use rand::prelude::SliceRandom;
+use rand::*;
+use rand_xoshiro::Xoroshiro128Plus;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct RandomAgent<G: Game> {
+	pub g: G,
+	rng: Xoroshiro128Plus,
+	reporter: Reporter<G::M>,
+}
+
+impl<G: Game> Ai<G> for RandomAgent<G> {
+	fn new(t: bool) -> Self {
+		Self {
+			g: G::new(t),
+			rng: Xoroshiro128Plus::from_rng(rand::thread_rng()).unwrap(),
+			reporter: Reporter::default(),
+		}
+	}
+	fn state(&self) -> State {
+		self.g.state()
+	}
+	fn print2game(&self) {
+		eprintln!("{}", self.g)
+	}
+
+	fn get_game(&self) -> &G {
+		&self.g
+	}
+
+	fn turn(&self) -> bool {
+		self.g.turn()
+	}
+	fn think(&mut self, _limits: Limits) -> G::M {
+		let m = *self.g.get_moves().choose(&mut self.rng).unwrap();
+		let info = SearchInfo::new(0, None, 0, Duration::ZERO, vec![m]);
+		self.reporter.report(&info);
+		m
+	}
+	fn mov(&mut self, m: &G::M) {
+		self.g.mov(m);
+	}
+	// answers straight away anyway
+	fn set_stop(&mut self, _stop: Arc<AtomicBool>) {}
+	fn set_seed(&mut self, seed: u64) {
+		self.rng = Xoroshiro128Plus::seed_from_u64(seed);
+	}
+	fn set_observer(&mut self, observer: Observer<G::M>) {
+		self.reporter.set(observer);
+	}
+}
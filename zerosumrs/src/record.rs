@@ -0,0 +1,278 @@
+use crate::game::*;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// tags written by compete and the client, any other tag is kept as is
+pub const WHITE: &str = "White";
+pub const BLACK: &str = "Black";
+pub const WHITE_ENGINE: &str = "WhiteEngine";
+pub const BLACK_ENGINE: &str = "BlackEngine";
+pub const TIME_LIMIT: &str = "TimeLimit";
+pub const SEED: &str = "Seed";
+pub const RESULT: &str = "Result";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedMove<M> {
+	pub mov: M,
+	// what the engine thought of the move, from white's point of view
+	pub score: Option<i64>,
+	pub depth: Option<u32>,
+}
+
+// PGN-like game record:
+// [White "us"]
+// [Result "1-0"]
+//
+// 1. e3-d3 {score 12 depth 5} a4-b4 2. d3-e3 ... 1-0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord<G: Game> {
+	pub tags: Vec<(String, String)>,
+	pub moves: Vec<RecordedMove<G::M>>,
+}
+
+impl<G: Game> Default for GameRecord<G> {
+	fn default() -> Self {
+		GameRecord {
+			tags: Vec::new(),
+			moves: Vec::new(),
+		}
+	}
+}
+
+pub fn result_str(s: State) -> &'static str {
+	match s {
+		State::Win => "1-0",
+		State::Lose => "0-1",
+		State::Draw => "1/2-1/2",
+		State::Going => "*",
+	}
+}
+
+fn parse_result(s: &str) -> Option<State> {
+	match s {
+		"1-0" => Some(State::Win),
+		"0-1" => Some(State::Lose),
+		"1/2-1/2" => Some(State::Draw),
+		"*" => Some(State::Going),
+		_ => None,
+	}
+}
+
+impl<G: Game> GameRecord<G> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	pub fn tag(&self, key: &str) -> Option<&str> {
+		self.tags
+			.iter()
+			.find(|(k, _)| k == key)
+			.map(|(_, v)| v.as_str())
+	}
+	pub fn set_tag(&mut self, key: &str, value: impl ToString) {
+		match self.tags.iter_mut().find(|(k, _)| k == key) {
+			Some((_, v)) => *v = value.to_string(),
+			None => self.tags.push((key.to_string(), value.to_string())),
+		}
+	}
+	pub fn result(&self) -> Option<State> {
+		self.tag(RESULT).and_then(parse_result)
+	}
+	pub fn set_result(&mut self, s: State) {
+		self.set_tag(RESULT, result_str(s));
+	}
+	pub fn push(&mut self, m: G::M) {
+		self.push_with_info(m, None, None);
+	}
+	pub fn push_with_info(&mut self, m: G::M, score: Option<i64>, depth: Option<u32>) {
+		self.moves.push(RecordedMove {
+			mov: m,
+			score,
+			depth,
+		});
+	}
+	// every position of the game, from the start to the last move
+	pub fn replay(&self) -> Result<Vec<G>, ReplayError> {
+		self.replay_from(G::new(true))
+	}
+	pub fn replay_from(&self, mut g: G) -> Result<Vec<G>, ReplayError> {
+		let mut ans = Vec::with_capacity(self.moves.len() + 1);
+		for (i, m) in self.moves.iter().enumerate() {
+			if g.state() != State::Going {
+				return Err(ReplayError::GameOver(i));
+			}
+			ans.push(g.clone());
+			g.mov(&m.mov);
+		}
+		if let Some(s) = self.result() {
+			if s != State::Going && s != g.state() {
+				return Err(ReplayError::Result(g.state()));
+			}
+		}
+		ans.push(g);
+		Ok(ans)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+	// the game was already over before this ply
+	GameOver(usize),
+	// the Result tag doesn't match the final position
+	Result(State),
+}
+
+impl std::fmt::Display for ReplayError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::GameOver(i) => write!(f, "game is over before ply {}", i + 1),
+			Self::Result(s) => write!(f, "result doesn't match final state {:?}", s),
+		}
+	}
+}
+
+impl std::error::Error for ReplayError {}
+
+impl<G: Game> Display for GameRecord<G>
+where
+	G::M: Display,
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for (k, v) in self.tags.iter() {
+			writeln!(
+				f,
+				"[{} \"{}\"]",
+				k,
+				v.replace('\\', "\\\\").replace('"', "\\\"")
+			)?;
+		}
+		writeln!(f)?;
+		for (i, m) in self.moves.iter().enumerate() {
+			if i % 2 == 0 {
+				if i != 0 {
+					writeln!(f)?;
+				}
+				write!(f, "{}. ", i / 2 + 1)?;
+			} else {
+				write!(f, " ")?;
+			}
+			write!(f, "{}", m.mov)?;
+			match (m.score, m.depth) {
+				(None, None) => {}
+				(Some(s), None) => write!(f, " {{score {}}}", s)?,
+				(None, Some(d)) => write!(f, " {{depth {}}}", d)?,
+				(Some(s), Some(d)) => write!(f, " {{score {} depth {}}}", s, d)?,
+			}
+		}
+		if !self.moves.is_empty() {
+			write!(f, " ")?;
+		}
+		writeln!(f, "{}", self.tag(RESULT).unwrap_or("*"))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRecordError {
+	// line of the malformed tag
+	Tag(String),
+	Move(String),
+	Comment(String),
+	// a comment before any move
+	Orphan(String),
+	Unterminated,
+}
+
+impl std::fmt::Display for ParseRecordError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Tag(s) => write!(f, "malformed tag '{}'", s),
+			Self::Move(s) => write!(f, "invalid move '{}'", s),
+			Self::Comment(s) => write!(f, "invalid comment '{}'", s),
+			Self::Orphan(s) => write!(f, "comment '{}' doesn't follow a move", s),
+			Self::Unterminated => write!(f, "unterminated comment"),
+		}
+	}
+}
+
+impl std::error::Error for ParseRecordError {}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+	let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+	let (key, value) = inner.split_once(' ')?;
+	let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+	let mut ans = String::with_capacity(value.len());
+	let mut chars = value.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => ans.push(chars.next()?),
+			'"' => return None,
+			c => ans.push(c),
+		}
+	}
+	Some((key.to_string(), ans))
+}
+
+impl<G: Game> FromStr for GameRecord<G>
+where
+	G::M: FromStr,
+{
+	type Err = ParseRecordError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut ans = GameRecord::new();
+		let mut text = String::new();
+		for line in s.lines().map(str::trim) {
+			if line.starts_with('[') {
+				let tag = parse_tag(line).ok_or_else(|| ParseRecordError::Tag(line.to_string()))?;
+				ans.tags.push(tag);
+			} else {
+				text.push_str(line);
+				text.push(' ');
+			}
+		}
+		let mut rest = text.as_str();
+		loop {
+			rest = rest.trim_start();
+			if rest.is_empty() {
+				break;
+			}
+			if let Some(r) = rest.strip_prefix('{') {
+				let end = r.find('}').ok_or(ParseRecordError::Unterminated)?;
+				let comment = &r[..end];
+				rest = &r[end + 1..];
+				let m = ans
+					.moves
+					.last_mut()
+					.ok_or_else(|| ParseRecordError::Orphan(comment.to_string()))?;
+				let words: Vec<&str> = comment.split_whitespace().collect();
+				for kv in words.chunks(2) {
+					let err = || ParseRecordError::Comment(comment.to_string());
+					match kv {
+						["score", v] => m.score = Some(v.parse().map_err(|_| err())?),
+						["depth", v] => m.depth = Some(v.parse().map_err(|_| err())?),
+						_ => return Err(err()),
+					}
+				}
+				continue;
+			}
+			let end = rest
+				.find(|c: char| c.is_whitespace() || c == '{')
+				.unwrap_or(rest.len());
+			let token = &rest[..end];
+			rest = &rest[end..];
+			// move numbers, and the result at the end which the tag overrides
+			if token.ends_with('.') {
+				continue;
+			}
+			if parse_result(token).is_some() {
+				if ans.tag(RESULT).is_none() {
+					ans.set_tag(RESULT, token);
+				}
+				continue;
+			}
+			let m = token
+				.parse()
+				.map_err(|_| ParseRecordError::Move(token.to_string()))?;
+			ans.push(m);
+		}
+		Ok(ans)
+	}
+}
@@ -0,0 +1,75 @@
+use crate::game::*;
+use crate::heuristic::Heuristic;
+use rand::prelude::SliceRandom;
+use rand::*;
+use rand_xoshiro::Xoroshiro128Plus;
+use std::marker::PhantomData;
+
+// how the monte carlo engines finish a playout from a new leaf of the tree
+pub trait Rollout<G: Game> {
+	// chance that white wins from g, which can be played on. moves is a
+	// buffer to fill moves into
+	fn play(g: &mut G, moves: &mut Vec<G::M>, rng: &mut Xoroshiro128Plus) -> f64;
+}
+
+fn result(s: State) -> f64 {
+	match s {
+		State::Win => 1.0,
+		State::Lose => 0.0,
+		_ => 0.5,
+	}
+}
+
+// uniformly random moves until the game ends
+pub struct RandomRollout;
+
+impl<G: Game> Rollout<G> for RandomRollout {
+	fn play(g: &mut G, moves: &mut Vec<G::M>, rng: &mut Xoroshiro128Plus) -> f64 {
+		while g.state() == State::Going {
+			g.fill_moves(moves);
+			let m = moves.choose(rng).unwrap();
+			g.mov(m);
+		}
+		result(g.state())
+	}
+}
+
+// epsilon greedy: the move H likes best, or a random one EPSILON percent of
+// the time. after PLIES plies the game is cut short and H::win_probability is
+// the result, 0 plays until the end
+pub struct HeuristicRollout<H, const EPSILON: u32 = 10, const PLIES: u32 = 0>(PhantomData<H>);
+
+impl<G: Game, H: Heuristic<G>, const EPSILON: u32, const PLIES: u32> Rollout<G>
+	for HeuristicRollout<H, EPSILON, PLIES>
+{
+	fn play(g: &mut G, moves: &mut Vec<G::M>, rng: &mut Xoroshiro128Plus) -> f64 {
+		let mut ply = 0;
+		while g.state() == State::Going {
+			if PLIES != 0 && ply == PLIES {
+				return H::win_probability(g);
+			}
+			ply += 1;
+			g.fill_moves(moves);
+			let m = if rng.next_u32() % 100 < EPSILON {
+				*moves.choose(rng).unwrap()
+			} else {
+				let white = g.turn();
+				*moves
+					.iter()
+					.max_by_key(|m| {
+						let rb = g.mov_with_rollback(m);
+						let v = H::eval(g);
+						g.rollback(rb);
+						if white {
+							v
+						} else {
+							v.saturating_neg()
+						}
+					})
+					.unwrap()
+			};
+			g.mov(&m);
+		}
+		result(g.state())
+	}
+}
@@ -143,6 +143,128 @@ pub const CAPTURE_AID: u128 = 0b000000000000000000000010100000000010000000000000
 */
 pub const GOAL: u128 = 0b00000000000000000000110001100010000000100100000001000000000000000000000000000000000001000000010010000000100011000110000000000000;
 
+// squares on the border of the board, citadels excluded
+pub const EDGE: u128 = {
+	let mut res = 0;
+	let mut i = 0;
+	while i < 9 {
+		res |= 1u128 << (12 + i);
+		res |= 1u128 << (100 + i);
+		res |= 1u128 << (12 + i * 11);
+		res |= 1u128 << (20 + i * 11);
+		i += 1;
+	}
+	res & !(CITADELS_0 | CITADELS_1)
+};
+
+// the 81 squares of the board
+pub const BOARD: u128 = {
+	let mut res = 0;
+	let mut i = 0;
+	while i < 81 {
+		res |= 1u128 << (i / 9 * 11 + i % 9 + 12);
+		i += 1;
+	}
+	res
+};
+
+pub const CORNERS: u128 = (1u128 << 12) | (1u128 << 20) | (1u128 << 100) | (1u128 << 108);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KingCapture {
+	// surrounded on four sides on or next to the throne, sandwiched elsewhere
+	NearThrone,
+	// sandwiched like any other pawn
+	Two,
+	// always surrounded on four sides
+	Four,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Escape {
+	// the marked escape squares
+	Goal,
+	// any border square outside the citadels
+	Edge,
+	// the four corners
+	Corner,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rules {
+	// the king helps the defenders capture
+	pub armed_king: bool,
+	// the throne counts as a capturer
+	pub hostile_throne: bool,
+	pub king_capture: KingCapture,
+	pub escape: Escape,
+}
+
+impl Rules {
+	pub const ASHTON: Rules = Rules {
+		armed_king: true,
+		hostile_throne: true,
+		king_capture: KingCapture::NearThrone,
+		escape: Escape::Goal,
+	};
+	pub const SEABATTLE: Rules = Rules {
+		armed_king: false,
+		hostile_throne: false,
+		king_capture: KingCapture::Four,
+		escape: Escape::Edge,
+	};
+	pub const BRANDUBH: Rules = Rules {
+		armed_king: true,
+		hostile_throne: true,
+		king_capture: KingCapture::Two,
+		escape: Escape::Corner,
+	};
+	pub const fn capture_aid(&self) -> u128 {
+		if self.hostile_throne {
+			CAPTURE_AID
+		} else {
+			CAPTURE_AID & !START_K
+		}
+	}
+	pub const fn goal(&self) -> u128 {
+		match self.escape {
+			Escape::Goal => GOAL,
+			Escape::Edge => EDGE,
+			Escape::Corner => CORNERS,
+		}
+	}
+}
+
+impl Default for Rules {
+	fn default() -> Self {
+		Rules::ASHTON
+	}
+}
+
+const fn splitmix64(x: u64) -> u64 {
+	let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+	z ^ (z >> 31)
+}
+
+// zobrist keys for attackers, defenders and king on every square
+pub const ZOBRIST: [[u64; 128]; 3] = {
+	let mut res = [[0; 128]; 3];
+	let mut i = 0;
+	while i < 3 {
+		let mut p = 0;
+		while p < 128 {
+			res[i][p] = splitmix64((i * 128 + p) as u64);
+			p += 1;
+		}
+		i += 1;
+	}
+	res
+};
+// xored in when the attackers are to move
+pub const ZOBRIST_TURN: u64 = splitmix64(3 * 128);
+
 pub const MASKS: [u128; 129] = {
 	let mut res = [
 		1,
@@ -281,13 +403,103 @@ pub const MASKS: [u128; 129] = {
 // a |= 1u128<<p;
 // (a>>p)&1 != 0
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+fn zobrist_of(kind: usize, mut pieces: u128) -> u64 {
+	let mut ans = 0;
+	while pieces != 0 {
+		let p = pieces.trailing_zeros() as usize;
+		pieces ^= 1u128 << p;
+		ans ^= ZOBRIST[kind][p];
+	}
+	ans
+}
+
+// pieces next to `to` with a capturer right behind them
+fn sandwiched(to: u128, capturers: u128) -> u128 {
+	((to << 1) & (capturers >> 1))
+		| ((to >> 1) & (capturers << 1))
+		| ((to << 11) & (capturers >> 11))
+		| ((to >> 11) & (capturers << 11))
+}
+
+// squares reachable from p along rows and columns, blocked must include the border
+fn reach(p: u8, blocked: u128) -> u128 {
+	let mut ans = 0;
+	for step in [1, -1, 11, -11].iter() {
+		let mut i = p as i32 + step;
+		while (blocked >> i) & 1 == 0 {
+			ans |= 1u128 << i;
+			i += step;
+		}
+	}
+	ans
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Tablut {
 	pub a: u128,
 	pub d: u128,
 	pub k: u128,
 	pub turn: u32,
 	pub state: State,
+	pub rules: Rules,
+	hash: u64,
+	// plies since the last capture, older positions can't repeat
+	reversible: u32,
+	history: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rollback {
+	a: u128,
+	d: u128,
+	k: u128,
+	turn: u32,
+	hash: u64,
+	reversible: u32,
+}
+
+// what the move undone by a Rollback did. after is the position the move
+// led to, the rollback only keeps the pieces from before it
+impl Rollback {
+	pub fn mov(&self, after: &Tablut) -> Move {
+		let (ca, cd, ck) = self.capture_set(after);
+		// the squares the moving piece left and reached
+		let moved = (self.a ^ after.a ^ ca) | (self.d ^ after.d ^ cd) | (self.k ^ after.k ^ ck);
+		if moved == 0 {
+			return Move::NONE;
+		}
+		let from = moved & (self.a | self.d | self.k);
+		let to = moved ^ from;
+		Move::new(from.trailing_zeros() as u8, to.trailing_zeros() as u8)
+	}
+	// pieces captured as attackers, defenders and king bitboards. only the
+	// side that didn't move loses pieces
+	pub fn capture_set(&self, after: &Tablut) -> (u128, u128, u128) {
+		if self.turn & 1 == 0 {
+			(self.a & !after.a, 0, 0)
+		} else {
+			(0, self.d & !after.d, self.k & !after.k)
+		}
+	}
+	// captured pieces and their squares as (x, y)
+	pub fn captures(&self, after: &Tablut) -> Vec<(Tile, (u8, u8))> {
+		let (ca, cd, ck) = self.capture_set(after);
+		let mut ans = Vec::new();
+		for &(t, mut bb) in [(Tile::A, ca), (Tile::D, cd), (Tile::K, ck)].iter() {
+			while bb != 0 {
+				let p = bb.trailing_zeros() as u8;
+				bb ^= 1u128 << p;
+				ans.push((t, coords(p)));
+			}
+		}
+		ans
+	}
+	pub fn king_captured(&self, after: &Tablut) -> bool {
+		self.k != 0 && after.k == 0
+	}
+	pub fn king_escaped(&self, after: &Tablut) -> bool {
+		after.k & !self.k & after.rules.goal() != 0
+	}
 }
 
 impl Default for Tablut {
@@ -303,7 +515,138 @@ pub enum Tile {
 	D,
 	K,
 }
+
+// padded bitboard index of the board coordinates (x, y), (0, 0) is a1
+pub const fn square((x, y): (u8, u8)) -> u8 {
+	y * 11 + x + 12
+}
+pub const fn coords(p: u8) -> (u8, u8) {
+	(p % 11 - 1, p / 11 - 1)
+}
+// server notation of a square, column letter and row number from the top.
+// p has to be on the board, Move::NONE has no squares
+pub fn square_name(p: u8) -> String {
+	let (x, y) = coords(p);
+	format!("{}{}", (b'a' + x) as char, y + 1)
+}
+
+// move between two padded squares, written e4-e7
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Move {
+	pub from: u8,
+	pub to: u8,
+}
+
+impl Move {
+	// played when the side to move has no legal move, and loses
+	pub const NONE: Move = Move { from: 0, to: 0 };
+	pub const fn new(from: u8, to: u8) -> Self {
+		Move { from, to }
+	}
+	pub const fn from_coords(from: (u8, u8), to: (u8, u8)) -> Self {
+		Move {
+			from: square(from),
+			to: square(to),
+		}
+	}
+	// None for Move::NONE
+	pub const fn coords(&self) -> Option<((u8, u8), (u8, u8))> {
+		if self.is_none() {
+			None
+		} else {
+			Some((coords(self.from), coords(self.to)))
+		}
+	}
+	pub const fn is_none(&self) -> bool {
+		self.from == 0
+	}
+}
+
+impl std::fmt::Display for Move {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.is_none() {
+			write!(f, "none")
+		} else {
+			write!(f, "{}-{}", square_name(self.from), square_name(self.to))
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMoveError(pub String);
+
+impl std::fmt::Display for ParseMoveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"invalid move '{}', expected something like e4-e7",
+			self.0
+		)
+	}
+}
+
+impl std::error::Error for ParseMoveError {}
+
+impl std::str::FromStr for Move {
+	type Err = ParseMoveError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s == "none" {
+			return Ok(Move::NONE);
+		}
+		let parse_square = |x: &str| match x.as_bytes() {
+			[c @ b'a'..=b'i', r @ b'1'..=b'9'] => Some((c - b'a', r - b'1')),
+			_ => None,
+		};
+		match s.split_once('-') {
+			Some((from, to)) => match (parse_square(from), parse_square(to)) {
+				(Some(from), Some(to)) => Ok(Move::from_coords(from, to)),
+				_ => Err(ParseMoveError(s.to_string())),
+			},
+			None => Err(ParseMoveError(s.to_string())),
+		}
+	}
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+	GameOver,
+	// a square index outside the board
+	OffBoard(u8),
+	// passing is only allowed without legal moves
+	Pass,
+	// squares as (x, y)
+	Empty((u8, u8)),
+	WrongSide((u8, u8)),
+	// not along a row or a column
+	NotStraight,
+	Blocked((u8, u8)),
+	// entering a citadel or the throne
+	Citadel((u8, u8)),
+}
+
+impl std::fmt::Display for MoveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let name = |c: &(u8, u8)| square_name(square(*c));
+		match self {
+			Self::GameOver => write!(f, "the game is over"),
+			Self::OffBoard(p) => write!(f, "square {} is off the board", p),
+			Self::Pass => write!(f, "can't pass with legal moves available"),
+			Self::Empty(c) => write!(f, "no piece on {}", name(c)),
+			Self::WrongSide(c) => write!(f, "the piece on {} belongs to the opponent", name(c)),
+			Self::NotStraight => write!(f, "pieces move along rows and columns"),
+			Self::Blocked(c) => write!(f, "the path is blocked on {}", name(c)),
+			Self::Citadel(c) => write!(f, "can't enter {}", name(c)),
+		}
+	}
+}
+
+impl std::error::Error for MoveError {}
+
 impl Tablut {
+	pub fn with_rules(t: bool, rules: Rules) -> Self {
+		let mut ans = Tablut::new(t);
+		ans.rules = rules;
+		ans
+	}
 	pub fn get_board(&self) -> [[Tile; 9]; 9] {
 		let mut ans = [[Tile::E; 9]; 9];
 		for y in 0..9 {
@@ -320,57 +663,196 @@ impl Tablut {
 		}
 		ans
 	}
+	// position with the given pieces, turn is the number of plies already played
+	pub fn from_board(board: &[[Tile; 9]; 9], turn: u32) -> Self {
+		Self::from_board_with_rules(board, turn, Rules::default())
+	}
+	pub fn from_board_with_rules(board: &[[Tile; 9]; 9], turn: u32, rules: Rules) -> Self {
+		let mut ans = Tablut::with_rules(true, rules);
+		ans.a = 0;
+		ans.d = 0;
+		ans.k = 0;
+		for (y, row) in board.iter().enumerate() {
+			for (x, t) in row.iter().enumerate() {
+				let p = y * 11 + x + 12;
+				match t {
+					Tile::A => ans.a |= 1u128 << p,
+					Tile::D => ans.d |= 1u128 << p,
+					Tile::K => ans.k |= 1u128 << p,
+					Tile::E => {}
+				}
+			}
+		}
+		ans.turn = turn;
+		ans.state = if ans.k == 0 {
+			State::Lose
+		} else if ans.k & ans.rules.goal() != 0 {
+			State::Win
+		} else {
+			State::Going
+		};
+		ans.hash = ans.zobrist();
+		ans.history.clear();
+		ans.history.push(ans.hash);
+		ans
+	}
+	// zobrist key computed from scratch, mov keeps self.hash equal to this
+	pub fn zobrist(&self) -> u64 {
+		let mut ans = if self.turn() { 0 } else { ZOBRIST_TURN };
+		ans ^= zobrist_of(0, self.a);
+		ans ^= zobrist_of(1, self.d);
+		ans ^= zobrist_of(2, self.k);
+		ans
+	}
+	// squares the piece on p can't enter or cross, other pieces aside:
+	// attackers can move inside the citadel they start from
+	fn camps(&self, p: u8) -> u128 {
+		if self.turn() {
+			BLOCK
+		} else if (CITADELS_0 >> p) & 1 != 0 {
+			BLOCK ^ CITADELS_0
+		} else if (CITADELS_1 >> p) & 1 != 0 {
+			BLOCK ^ CITADELS_1
+		} else {
+			BLOCK
+		}
+	}
+	// true if the current position already happened since the last capture
+	fn repeated(&self) -> bool {
+		self.history
+			.iter()
+			.rev()
+			.take(self.reversible as usize)
+			.skip(1)
+			.step_by(2)
+			.any(|x| *x == self.hash)
+	}
+	// pieces m would capture, as attackers, defenders and king bitboards
+	pub fn capture_set(&self, m: &Move) -> (u128, u128, u128) {
+		if m.is_none() {
+			return (0, 0, 0);
+		}
+		let moved = (1u128 << m.from) | (1u128 << m.to);
+		let to = 1u128 << m.to;
+		if self.turn() {
+			let king = (self.k >> m.from) & 1 != 0;
+			if king && !self.rules.armed_king {
+				return (0, 0, 0);
+			}
+			let mut capturers = self.d;
+			if self.rules.armed_king {
+				capturers |= self.k;
+			}
+			capturers = (capturers ^ moved) | self.rules.capture_aid();
+			(self.a & sandwiched(to, capturers), 0, 0)
+		} else {
+			let capturers = (self.a ^ moved) | self.rules.capture_aid();
+			let cd = self.d & sandwiched(to, capturers);
+			let sides = (to << 1) | (to >> 1) | (to << 11) | (to >> 11);
+			if self.k & sides == 0 {
+				return (0, cd, 0);
+			}
+			let surround = match self.rules.king_capture {
+				KingCapture::NearThrone => self.k & K_SAFETY != 0,
+				KingCapture::Two => false,
+				KingCapture::Four => true,
+			};
+			let ck = if surround {
+				let ksides = (self.k << 1) | (self.k >> 1) | (self.k << 11) | (self.k >> 11);
+				if (capturers & ksides).count_ones() == 4 {
+					self.k
+				} else {
+					0
+				}
+			} else {
+				self.k & sandwiched(to, capturers)
+			};
+			(0, cd, ck)
+		}
+	}
+	pub fn is_capture(&self, m: &Move) -> bool {
+		self.capture_set(m) != (0, 0, 0)
+	}
+	// the king escapes with m, or could escape with its next move
+	pub fn is_king_threat(&self, m: &Move) -> bool {
+		if m.is_none() || (self.k >> m.from) & 1 == 0 {
+			return false;
+		}
+		let goal = self.rules.goal();
+		if (goal >> m.to) & 1 != 0 {
+			return true;
+		}
+		let (ca, _, _) = self.capture_set(m);
+		let pawns = (self.a ^ ca) | self.d | (1u128 << m.to);
+		reach(m.to, BLOCK | pawns) & goal != 0
+	}
+	// moves that capture something
+	pub fn fill_captures(&self, ans: &mut Vec<Move>) {
+		self.fill_moves(ans);
+		ans.retain(|m| self.is_capture(m));
+	}
+	// king moves that escape or threaten to, captures excluded
+	pub fn fill_king_threats(&self, ans: &mut Vec<Move>) {
+		self.fill_moves(ans);
+		ans.retain(|m| !self.is_capture(m) && self.is_king_threat(m));
+	}
+	// everything else
+	pub fn fill_quiet(&self, ans: &mut Vec<Move>) {
+		self.fill_moves(ans);
+		ans.retain(|m| !self.is_capture(m) && !self.is_king_threat(m));
+	}
 }
 
 impl Game for Tablut {
-	type M = (u8, u8);
-	type S = Self;
-	type R = Self;
+	type M = Move;
+	type S = (u128, u128, u128, bool);
+	type R = Rollback;
+	type E = MoveError;
 	fn new(t: bool) -> Self {
-		Tablut {
+		let mut ans = Tablut {
 			a: START_A,
 			d: START_D,
 			k: START_K,
 			turn: if t { 0 } else { 1 },
 			state: State::Going,
-		}
+			rules: Rules::default(),
+			hash: 0,
+			reversible: 0,
+			history: Vec::with_capacity(128),
+		};
+		ans.hash = ans.zobrist();
+		ans.history.push(ans.hash);
+		ans
 	}
 	fn turn(&self) -> bool {
 		self.turn & 1 == 0
 	}
 
-	fn get_moves(&self) -> Vec<Self::M> {
-		let mut ans = Vec::<Self::M>::with_capacity(96);
+	fn fill_moves(&self, ans: &mut Vec<Self::M>) {
+		ans.clear();
+		ans.reserve(96);
 		let mut allies = if self.turn() { self.d | self.k } else { self.a };
 		let pawns = self.d | self.k | self.a;
 		while allies != 0 {
 			let p = allies.trailing_zeros() as u8;
 			allies ^= 1u128 << p;
-			let pass = !(if self.turn() {
-				BLOCK
-			} else if (CITADELS_0 >> p) & 1 != 0 {
-				BLOCK ^ CITADELS_0
-			} else if (CITADELS_1 >> p) & 1 != 0 {
-				BLOCK ^ CITADELS_1
-			} else {
-				BLOCK
-			} | pawns);
+			let pass = !(self.camps(p) | pawns);
 
 			let mut i = p as usize + 11;
 			while pass & MASKS[i] != 0 {
-				ans.push((p, i as u8));
+				ans.push(Move::new(p, i as u8));
 				i += 11;
 			}
 			i = p as usize - 11;
 			while pass & MASKS[i] != 0 {
-				ans.push((p, i as u8));
+				ans.push(Move::new(p, i as u8));
 				i -= 11;
 			}
 
 			let mut i = p + 1;
 			let p1 = (pass >> i) as u32;
 			for _ in 0..p1.trailing_ones() {
-				ans.push((p, i));
+				ans.push(Move::new(p, i));
 				i += 1;
 			}
 			i = p - 1;
@@ -378,36 +860,39 @@ impl Game for Tablut {
 			p1 |= !511;
 			let iter = p1.leading_ones() - 23;
 			for _ in 0..iter {
-				ans.push((p, i));
+				ans.push(Move::new(p, i));
 				i -= 1;
 			}
 		}
 		if ans.is_empty() {
-			ans.push((0, 0));
+			ans.push(Move::NONE);
 		}
-		ans
 	}
-	fn get_moves_sorted(&self) -> Vec<Self::M> {
-		let mut ans = self.get_moves();
+	fn fill_moves_sorted(&self, ans: &mut Vec<Self::M>) {
+		self.fill_moves(ans);
 		ans.sort_unstable_by_key(|m| {
 			const ORDI: [[u8; 9]; 2] = [
 				// 0,1,2,3,4,5,6,7,8   // old order
 				[9, 4, 5, 3, 6, 7, 2, 1, 0], // def, lower is better
 				[9, 5, 2, 4, 3, 7, 1, 6, 0], // atk, lower is better
 			];
-			let dif = if m.0 > m.1 { m.0 - m.1 } else { m.1 - m.0 };
+			let dif = if m.from > m.to {
+				m.from - m.to
+			} else {
+				m.to - m.from
+			};
 			let dist = if dif >= 11 { dif / 11 } else { dif };
 			if self.turn() {
-				if (self.k >> m.0) & 1 != 0 {
+				if (self.k >> m.from) & 1 != 0 {
 					ORDI[(self.turn & 1) as usize][dist as usize]
 				} else {
 					ORDI[(self.turn & 1) as usize][dist as usize] + 8
 				}
 			} else {
-				if (self.k >> (m.1 + 1)) & 1 != 0
-					|| (self.k >> (m.1 + 11)) & 1 != 0
-					|| (self.k >> (m.1 - 1)) & 1 != 0
-					|| (self.k >> (m.1 - 11)) & 1 != 0
+				if (self.k >> (m.to + 1)) & 1 != 0
+					|| (self.k >> (m.to + 11)) & 1 != 0
+					|| (self.k >> (m.to - 1)) & 1 != 0
+					|| (self.k >> (m.to - 11)) & 1 != 0
 				{
 					ORDI[(self.turn & 1) as usize][dist as usize]
 				} else {
@@ -415,102 +900,173 @@ impl Game for Tablut {
 				}
 			}
 		});
-		ans
+	}
+	// escapes first, a search short on nodes should not miss them
+	fn fill_forcing_moves(&self, ans: &mut Vec<Self::M>) {
+		self.fill_moves(ans);
+		ans.retain(|m| self.is_forcing(m));
+		let goal = self.rules.goal();
+		ans.sort_by_key(|m| (self.k >> m.from) & 1 == 0 || (goal >> m.to) & 1 == 0);
+	}
+	fn is_forcing(&self, m: &Self::M) -> bool {
+		self.is_capture(m) || self.is_king_threat(m)
+	}
+	// the king escapes next move unless the attackers stop it
+	fn is_threatened(&self) -> bool {
+		if self.turn() || self.k == 0 || self.state != State::Going {
+			return false;
+		}
+		let k = self.k.trailing_zeros() as u8;
+		reach(k, BLOCK | self.a | self.d) & self.rules.goal() != 0
+	}
+	const SQUARES: usize = 121;
+	fn squares(m: &Self::M) -> (usize, usize) {
+		(m.from as usize, m.to as usize)
+	}
+	fn from_squares(from: usize, to: usize) -> Self::M {
+		Move::new(from as u8, to as u8)
 	}
 	fn get_static_state(&self) -> Self::S {
-		*self
+		(self.a, self.d, self.k, self.turn())
+	}
+	fn get_hash(&self) -> u64 {
+		self.hash
+	}
+	fn get_canonical_hash(&self) -> u64 {
+		let (_, (a, d, k, t)) = self.canonical();
+		let mut ans = if t { 0 } else { ZOBRIST_TURN };
+		ans ^= zobrist_of(0, a);
+		ans ^= zobrist_of(1, d);
+		ans ^= zobrist_of(2, k);
+		ans
 	}
 	fn state(&self) -> State {
 		self.state
 	}
-	fn mov(&mut self, m: &Self::M) {
-		if m.0 == 0 {
-			self.state = if self.turn() { State::Win } else { State::Lose };
-			return;
+	fn check_mov(&self, m: &Self::M) -> Result<(), Self::E> {
+		if self.state != State::Going {
+			return Err(MoveError::GameOver);
 		}
-		if self.turn() {
-			// def
-			if (self.d >> m.0) & 1 != 0 {
-				// d moved
-				self.d &= !(1u128 << m.0);
-				self.d |= 1u128 << m.1;
-			} else {
-				// k moved
-				self.k &= !(1u128 << m.0);
-				self.k |= 1u128 << m.1;
-			}
-			let capturers = self.d | self.k | CAPTURE_AID;
-			if (capturers >> (m.1 + 2)) & 1 != 0 {
-				self.a &= !(1u128 << (m.1 + 1));
-			}
-			if (capturers >> (m.1 + 22)) & 1 != 0 {
-				self.a &= !(1u128 << (m.1 + 11));
-			}
-			if (capturers >> (m.1 - 2)) & 1 != 0 {
-				self.a &= !(1u128 << (m.1 - 1));
-			}
-			if (capturers >> (m.1 - 22)) & 1 != 0 {
-				self.a &= !(1u128 << (m.1 - 11));
+		if m.is_none() {
+			return match self.get_moves()[0].is_none() {
+				true => Ok(()),
+				false => Err(MoveError::Pass),
+			};
+		}
+		for p in [m.from, m.to].iter() {
+			if *p >= 128 || (BOARD >> p) & 1 == 0 {
+				return Err(MoveError::OffBoard(*p));
 			}
+		}
+		let pawns = self.a | self.d | self.k;
+		let allies = if self.turn() { self.d | self.k } else { self.a };
+		if (allies >> m.from) & 1 == 0 {
+			return Err(match (pawns >> m.from) & 1 {
+				0 => MoveError::Empty(coords(m.from)),
+				_ => MoveError::WrongSide(coords(m.from)),
+			});
+		}
+		let ((fx, fy), (tx, ty)) = (coords(m.from), coords(m.to));
+		let step = if fy == ty && fx != tx {
+			1
+		} else if fx == tx && fy != ty {
+			11
 		} else {
-			// atk
-			self.a &= !(1u128 << m.0);
-			self.a |= 1u128 << m.1;
-			let capturers = self.a | CAPTURE_AID;
-			if (capturers >> (m.1 + 2)) & 1 != 0 {
-				self.d &= !(1u128 << (m.1 + 1));
-			}
-			if (capturers >> (m.1 + 22)) & 1 != 0 {
-				self.d &= !(1u128 << (m.1 + 11));
-			}
-			if (capturers >> (m.1 - 2)) & 1 != 0 {
-				self.d &= !(1u128 << (m.1 - 1));
+			return Err(MoveError::NotStraight);
+		};
+		let camps = self.camps(m.from);
+		let mut p = m.from;
+		while p != m.to {
+			p = if m.to > m.from { p + step } else { p - step };
+			if (pawns >> p) & 1 != 0 {
+				return Err(MoveError::Blocked(coords(p)));
 			}
-			if (capturers >> (m.1 - 22)) & 1 != 0 {
-				self.d &= !(1u128 << (m.1 - 11));
-			}
-			let sides =
-				(1u128 << (m.1 + 1)) | (1u128 << (m.1 + 11)) | (1u128 << (m.1 - 1)) | (1u128 << (m.1 - 11));
-			if self.k & sides != 0 {
-				if self.k & K_SAFETY != 0 {
-					let kp = self.k.trailing_zeros();
-					let ksides =
-						(1u128 << (kp + 1)) | (1u128 << (kp + 11)) | (1u128 << (kp - 1)) | (1u128 << (kp - 11));
-					if (capturers & ksides).count_ones() == 4 {
-						self.k = 0;
-					}
-				} else {
-					if (capturers >> (m.1 + 2)) & 1 != 0 {
-						self.k &= !(1u128 << (m.1 + 1));
-					}
-					if (capturers >> (m.1 + 22)) & 1 != 0 {
-						self.k &= !(1u128 << (m.1 + 11));
-					}
-					if (capturers >> (m.1 - 2)) & 1 != 0 {
-						self.k &= !(1u128 << (m.1 - 1));
-					}
-					if (capturers >> (m.1 - 22)) & 1 != 0 {
-						self.k &= !(1u128 << (m.1 - 11));
-					}
-				}
+			if (camps >> p) & 1 != 0 {
+				return Err(MoveError::Citadel(coords(p)));
 			}
 		}
+		Ok(())
+	}
+	fn mov(&mut self, m: &Self::M) {
+		if m.is_none() {
+			self.state = if self.turn() { State::Win } else { State::Lose };
+			return;
+		}
+		let (ca, cd, ck) = self.capture_set(m);
+		let moved = (1u128 << m.from) | (1u128 << m.to);
+		let kind = if (self.a >> m.from) & 1 != 0 {
+			self.a ^= moved;
+			0
+		} else if (self.d >> m.from) & 1 != 0 {
+			self.d ^= moved;
+			1
+		} else {
+			self.k ^= moved;
+			2
+		};
+		self.hash ^= ZOBRIST[kind][m.from as usize] ^ ZOBRIST[kind][m.to as usize];
+		self.a ^= ca;
+		self.d ^= cd;
+		self.k ^= ck;
+		self.hash ^= zobrist_of(0, ca) ^ zobrist_of(1, cd) ^ zobrist_of(2, ck);
+		let captured = ca | cd | ck;
 		self.turn += 1;
+		self.hash ^= ZOBRIST_TURN;
+		if captured == 0 {
+			self.reversible += 1;
+		} else {
+			self.reversible = 0;
+		}
 		self.state = if self.k == 0 {
 			State::Lose
-		} else if self.k & GOAL != 0 {
+		} else if self.k & self.rules.goal() != 0 {
 			State::Win
+		} else if self.repeated() {
+			State::Draw
 		} else {
 			State::Going
-		}
+		};
+		self.history.push(self.hash);
 	}
 	fn mov_with_rollback(&mut self, m: &Self::M) -> Self::R {
-		let t = *self;
+		let rb = Rollback {
+			a: self.a,
+			d: self.d,
+			k: self.k,
+			turn: self.turn,
+			hash: self.hash,
+			reversible: self.reversible,
+		};
 		self.mov(m);
-		t
+		rb
+	}
+	fn rollback(&mut self, rb: Self::R) {
+		if self.turn != rb.turn {
+			self.history.pop();
+		}
+		self.a = rb.a;
+		self.d = rb.d;
+		self.k = rb.k;
+		self.turn = rb.turn;
+		self.hash = rb.hash;
+		self.reversible = rb.reversible;
+		self.state = State::Going;
 	}
-	fn rollback(&mut self, rbf: Self::R) {
-		*self = rbf;
+	fn null_mov(&mut self) -> Option<Self::R> {
+		let rb = Rollback {
+			a: self.a,
+			d: self.d,
+			k: self.k,
+			turn: self.turn,
+			hash: self.hash,
+			reversible: self.reversible,
+		};
+		self.turn += 1;
+		self.hash ^= ZOBRIST_TURN;
+		// positions before the pass don't count as repetitions after it
+		self.reversible = 0;
+		self.history.push(self.hash);
+		Some(rb)
 	}
 }
 impl std::fmt::Display for Tablut {
@@ -535,3 +1091,245 @@ impl std::fmt::Display for Tablut {
 		Ok(())
 	}
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTablutError {
+	// expected board, side to move and turn separated by spaces
+	Fields(usize),
+	Ranks(usize),
+	RankLength(usize),
+	Piece(char),
+	// a piece that can't stand on this square, as (x, y)
+	Square(Tile, (u8, u8)),
+	Kings(usize),
+	Side(String),
+	Turn(String),
+	// the side to move doesn't match the parity of the turn
+	SideTurn,
+}
+
+impl std::fmt::Display for ParseTablutError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Fields(n) => write!(f, "expected 3 fields, found {}", n),
+			Self::Ranks(n) => write!(f, "expected 9 ranks, found {}", n),
+			Self::RankLength(y) => write!(f, "rank {} is not 9 squares long", y + 1),
+			Self::Piece(c) => write!(f, "unknown piece '{}'", c),
+			Self::Square(t, (x, y)) => {
+				write!(f, "{:?} can't stand on {}{}", t, (b'a' + x) as char, y + 1)
+			}
+			Self::Kings(n) => write!(f, "expected at most one king, found {}", n),
+			Self::Side(s) => write!(f, "expected side 'w' or 'b', found '{}'", s),
+			Self::Turn(s) => write!(f, "invalid turn '{}'", s),
+			Self::SideTurn => write!(f, "side to move doesn't match the turn"),
+		}
+	}
+}
+
+impl std::error::Error for ParseTablutError {}
+
+impl Tablut {
+	// ranks from the top separated by '/', digits for empty squares,
+	// then the side to move and the number of plies played:
+	// 3AAA3/4A4/4D4/A3D3A/AADDKDDAA/A3D3A/4D4/4A4/3AAA3 w 0
+	pub fn fen(&self) -> String {
+		let mut ans = String::with_capacity(64);
+		for (y, row) in self.get_board().iter().enumerate() {
+			if y != 0 {
+				ans.push('/');
+			}
+			let mut empty = 0;
+			for t in row.iter() {
+				let c = match t {
+					Tile::E => {
+						empty += 1;
+						continue;
+					}
+					Tile::A => 'A',
+					Tile::D => 'D',
+					Tile::K => 'K',
+				};
+				if empty != 0 {
+					ans.push((b'0' + empty) as char);
+					empty = 0;
+				}
+				ans.push(c);
+			}
+			if empty != 0 {
+				ans.push((b'0' + empty) as char);
+			}
+		}
+		ans.push_str(if self.turn() { " w " } else { " b " });
+		ans.push_str(&self.turn.to_string());
+		ans
+	}
+}
+
+impl std::str::FromStr for Tablut {
+	type Err = ParseTablutError;
+	// a position of the default rules
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_str_with_rules(s, Rules::default())
+	}
+}
+
+impl Tablut {
+	// the notation doesn't say which rules the game is played with
+	pub fn from_str_with_rules(s: &str, rules: Rules) -> Result<Self, ParseTablutError> {
+		let fields: Vec<&str> = s.split_whitespace().collect();
+		if fields.len() != 3 {
+			return Err(ParseTablutError::Fields(fields.len()));
+		}
+		let ranks: Vec<&str> = fields[0].split('/').collect();
+		if ranks.len() != 9 {
+			return Err(ParseTablutError::Ranks(ranks.len()));
+		}
+		let mut board = [[Tile::E; 9]; 9];
+		let mut kings = 0;
+		for (y, rank) in ranks.iter().enumerate() {
+			let mut x = 0;
+			for c in rank.chars() {
+				let t = match c {
+					'1'..='9' => {
+						x += c as usize - '0' as usize;
+						continue;
+					}
+					'A' => Tile::A,
+					'D' => Tile::D,
+					'K' => Tile::K,
+					_ => return Err(ParseTablutError::Piece(c)),
+				};
+				if x >= 9 {
+					return Err(ParseTablutError::RankLength(y));
+				}
+				let p = y * 11 + x + 12;
+				let forbidden = match t {
+					Tile::A => START_K,
+					Tile::K => BLOCK & !START_K,
+					_ => BLOCK,
+				};
+				if (forbidden >> p) & 1 != 0 {
+					return Err(ParseTablutError::Square(t, (x as u8, y as u8)));
+				}
+				if t == Tile::K {
+					kings += 1;
+				}
+				board[y][x] = t;
+				x += 1;
+			}
+			if x != 9 {
+				return Err(ParseTablutError::RankLength(y));
+			}
+		}
+		if kings > 1 {
+			return Err(ParseTablutError::Kings(kings));
+		}
+		let side = match fields[1] {
+			"w" => true,
+			"b" => false,
+			x => return Err(ParseTablutError::Side(x.to_string())),
+		};
+		let turn: u32 = fields[2]
+			.parse()
+			.map_err(|_| ParseTablutError::Turn(fields[2].to_string()))?;
+		if (turn & 1 == 0) != side {
+			return Err(ParseTablutError::SideTurn);
+		}
+		Ok(Tablut::from_board_with_rules(&board, turn, rules))
+	}
+}
+
+// one of the 8 rotations/reflections of the board, applied to (x, y) as:
+// bit 2 swaps x and y, then bit 0 mirrors x and bit 1 mirrors y
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Symmetry(u8);
+
+const SYMMETRY_SQUARES: [[u8; 128]; 8] = {
+	let mut res = [[0; 128]; 8];
+	let mut s = 0;
+	while s < 8 {
+		let mut p = 0;
+		while p < 128 {
+			res[s][p] = p as u8;
+			p += 1;
+		}
+		let mut y = 0;
+		while y < 9 {
+			let mut x = 0;
+			while x < 9 {
+				res[s][square((x, y)) as usize] = square(Symmetry(s as u8).coords((x, y)));
+				x += 1;
+			}
+			y += 1;
+		}
+		s += 1;
+	}
+	res
+};
+
+impl Symmetry {
+	pub const IDENTITY: Symmetry = Symmetry(0);
+	pub const ALL: [Symmetry; 8] = [
+		Symmetry(0),
+		Symmetry(1),
+		Symmetry(2),
+		Symmetry(3),
+		Symmetry(4),
+		Symmetry(5),
+		Symmetry(6),
+		Symmetry(7),
+	];
+	pub const fn coords(self, (x, y): (u8, u8)) -> (u8, u8) {
+		let (x, y) = if self.0 & 4 != 0 { (y, x) } else { (x, y) };
+		let x = if self.0 & 1 != 0 { 8 - x } else { x };
+		let y = if self.0 & 2 != 0 { 8 - y } else { y };
+		(x, y)
+	}
+	// same as coords, on padded bitboard indices
+	pub fn square(self, p: u8) -> u8 {
+		SYMMETRY_SQUARES[self.0 as usize][p as usize]
+	}
+	pub fn bitboard(self, mut b: u128) -> u128 {
+		if self.0 == 0 {
+			return b;
+		}
+		let mut ans = 0;
+		while b != 0 {
+			let p = b.trailing_zeros() as u8;
+			b ^= 1u128 << p;
+			ans |= 1u128 << self.square(p);
+		}
+		ans
+	}
+	pub fn mov(self, m: Move) -> Move {
+		if m.is_none() {
+			return m;
+		}
+		Move::new(self.square(m.from), self.square(m.to))
+	}
+	pub fn inverse(self) -> Symmetry {
+		if self.0 & 4 != 0 {
+			// mirroring x after the swap is mirroring y before it
+			Symmetry(4 | ((self.0 & 1) << 1) | ((self.0 & 2) >> 1))
+		} else {
+			self
+		}
+	}
+}
+
+impl Tablut {
+	// the symmetry taking this position to the representative of its class,
+	// and the static state of the representative.
+	// moves found there map back with sym.inverse().mov(m)
+	pub fn canonical(&self) -> (Symmetry, <Tablut as Game>::S) {
+		let mut ans = (Symmetry::IDENTITY, (self.k, self.a, self.d));
+		for s in Symmetry::ALL.iter().skip(1) {
+			let x = (s.bitboard(self.k), s.bitboard(self.a), s.bitboard(self.d));
+			if x < ans.1 {
+				ans = (*s, x);
+			}
+		}
+		let (k, a, d) = ans.1;
+		(ans.0, (a, d, k, self.turn()))
+	}
+}
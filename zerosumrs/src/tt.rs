@@ -0,0 +1,266 @@
+// fixed size transposition table, keyed by Game::get_hash
+
+use crate::game::*;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+	Exact,
+	// the value is at least val, from a beta cutoff
+	Lower,
+	// the value is at most val, no move reached alpha
+	Upper,
+}
+
+impl Bound {
+	// bound of a value searched with the window (a, b), scores are from white's
+	// point of view so this holds for both sides
+	pub fn of(val: i64, a: i64, b: i64) -> Self {
+		if val <= a {
+			Bound::Upper
+		} else if val >= b {
+			Bound::Lower
+		} else {
+			Bound::Exact
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry<M> {
+	pub key: u64,
+	pub val: i64,
+	pub depth: u32,
+	pub bound: Bound,
+	pub mov: M,
+	// search that wrote the entry, old entries get replaced first
+	pub age: u8,
+}
+
+impl<M> Entry<M> {
+	// the stored value if it decides a search of depth with the window (a, b)
+	pub fn cutoff(&self, depth: u32, a: i64, b: i64) -> Option<i64> {
+		if self.depth < depth {
+			return None;
+		}
+		match self.bound {
+			Bound::Exact => Some(self.val),
+			Bound::Lower if self.val >= b => Some(self.val),
+			Bound::Upper if self.val <= a => Some(self.val),
+			_ => None,
+		}
+	}
+}
+
+// each bucket has a depth-preferred slot and an always-replace slot
+pub struct TranspositionTable<M> {
+	buckets: Vec<[Option<Entry<M>>; 2]>,
+	age: u8,
+}
+
+impl<M: Copy> TranspositionTable<M> {
+	// 2^bits buckets
+	pub fn new(bits: u32) -> Self {
+		Self {
+			buckets: vec![[None; 2]; 1 << bits],
+			age: 0,
+		}
+	}
+	pub fn clear(&mut self) {
+		for b in self.buckets.iter_mut() {
+			*b = [None; 2];
+		}
+		self.age = 0;
+	}
+	// call before each search, so entries of the previous ones can be replaced
+	pub fn new_search(&mut self) {
+		self.age = self.age.wrapping_add(1);
+	}
+	fn bucket(&self, key: u64) -> usize {
+		key as usize & (self.buckets.len() - 1)
+	}
+	pub fn probe(&self, key: u64) -> Option<&Entry<M>> {
+		self.buckets[self.bucket(key)]
+			.iter()
+			.flatten()
+			.find(|e| e.key == key)
+	}
+	pub fn store(&mut self, key: u64, val: i64, depth: u32, bound: Bound, mov: M) {
+		let entry = Entry {
+			key,
+			val,
+			depth,
+			bound,
+			mov,
+			age: self.age,
+		};
+		let age = self.age;
+		let bucket = self.bucket(key);
+		let [deep, recent] = &mut self.buckets[bucket];
+		match deep {
+			Some(e) if e.key != key && e.depth > depth && e.age == age => {
+				*recent = Some(entry);
+			}
+			Some(e) => {
+				// the displaced entry still beats whatever is in the other slot
+				if e.key != key {
+					*recent = Some(*e);
+				} else if recent.is_some_and(|r| r.key == key) {
+					*recent = None;
+				}
+				*deep = Some(entry);
+			}
+			None => *deep = Some(entry),
+		}
+	}
+	// permille of the slots used by the current search
+	pub fn hashfull(&self) -> usize {
+		let n = self.buckets.len().min(500);
+		let used = self.buckets[..n]
+			.iter()
+			.flatten()
+			.flatten()
+			.filter(|e| e.age == self.age)
+			.count();
+		used * 1000 / (2 * n)
+	}
+}
+
+// line of stored best moves from g, up to the first one that isn't legal
+pub fn principal_variation<G: Game>(
+	g: &G,
+	max: u32,
+	probe: impl Fn(u64) -> Option<G::M>,
+) -> Vec<G::M> {
+	let mut g = g.clone();
+	let mut ans = Vec::new();
+	while ans.len() < max as usize && g.state() == State::Going {
+		match probe(g.get_hash()) {
+			Some(m) if g.is_legal(&m) => {
+				g.mov(&m);
+				ans.push(m);
+			}
+			_ => break,
+		}
+	}
+	ans
+}
+
+// same table shared by several search threads without locks. a slot is three
+// words and the first is the key xored with the other two, so a slot torn by
+// concurrent writes doesn't match its key and reads as a miss
+pub struct SharedTable<G: Game> {
+	slots: Vec<[AtomicU64; 3]>,
+	age: AtomicU8,
+	// only the move conversions of G are used, no G is stored
+	_ph: PhantomData<fn() -> G>,
+}
+
+impl<G: Game> SharedTable<G> {
+	// 2^bits buckets of two slots, like TranspositionTable
+	pub fn new(bits: u32) -> Self {
+		Self {
+			slots: (0..2usize << bits).map(|_| Default::default()).collect(),
+			age: AtomicU8::new(0),
+			_ph: PhantomData,
+		}
+	}
+	pub fn clear(&self) {
+		for slot in self.slots.iter() {
+			for w in slot.iter() {
+				w.store(0, Ordering::Relaxed);
+			}
+		}
+		self.age.store(0, Ordering::Relaxed);
+	}
+	pub fn new_search(&self) {
+		self.age.fetch_add(1, Ordering::Relaxed);
+	}
+	fn bucket(&self, key: u64) -> usize {
+		(key as usize & (self.slots.len() / 2 - 1)) * 2
+	}
+	// moves are kept as their squares, the bound in 2 bits where 0 is empty
+	fn load(&self, i: usize) -> Option<Entry<G::M>> {
+		let [k, v, d] = &self.slots[i];
+		let (k, v, d) = (
+			k.load(Ordering::Relaxed),
+			v.load(Ordering::Relaxed),
+			d.load(Ordering::Relaxed),
+		);
+		let bound = match (d >> 48) & 3 {
+			1 => Bound::Exact,
+			2 => Bound::Lower,
+			3 => Bound::Upper,
+			_ => return None,
+		};
+		Some(Entry {
+			key: k ^ v ^ d,
+			val: v as i64,
+			depth: ((d >> 32) & 0xffff) as u32,
+			bound,
+			mov: G::from_squares((d & 0xffff) as usize, ((d >> 16) & 0xffff) as usize),
+			age: (d >> 56) as u8,
+		})
+	}
+	fn write(&self, i: usize, e: &Entry<G::M>) {
+		let (from, to) = G::squares(&e.mov);
+		let bound = match e.bound {
+			Bound::Exact => 1,
+			Bound::Lower => 2,
+			Bound::Upper => 3,
+		};
+		let v = e.val as u64;
+		let d = from as u64
+			| (to as u64) << 16
+			| (e.depth.min(0xffff) as u64) << 32
+			| bound << 48
+			| (e.age as u64) << 56;
+		let [wk, wv, wd] = &self.slots[i];
+		wk.store(e.key ^ v ^ d, Ordering::Relaxed);
+		wv.store(v, Ordering::Relaxed);
+		wd.store(d, Ordering::Relaxed);
+	}
+	pub fn probe(&self, key: u64) -> Option<Entry<G::M>> {
+		let i = self.bucket(key);
+		(i..i + 2)
+			.filter_map(|i| self.load(i))
+			.find(|e| e.key == key)
+	}
+	pub fn store(&self, key: u64, val: i64, depth: u32, bound: Bound, mov: G::M) {
+		let age = self.age.load(Ordering::Relaxed);
+		let entry = Entry {
+			key,
+			val,
+			depth,
+			bound,
+			mov,
+			age,
+		};
+		let i = self.bucket(key);
+		match self.load(i) {
+			Some(e) if e.key != key && e.depth > depth && e.age == age => {
+				self.write(i + 1, &entry);
+			}
+			Some(e) => {
+				if e.key != key {
+					self.write(i + 1, &e);
+				}
+				self.write(i, &entry);
+			}
+			None => self.write(i, &entry),
+		}
+	}
+	// permille of the slots used by the current search
+	pub fn hashfull(&self) -> usize {
+		let age = self.age.load(Ordering::Relaxed);
+		let n = self.slots.len().min(1000);
+		let used = (0..n)
+			.filter_map(|i| self.load(i))
+			.filter(|e| e.age == age)
+			.count();
+		used * 1000 / n
+	}
+}